@@ -53,7 +53,7 @@ class FundingCard extends ReactiveComponent {
                         <br />
                     </Card.Content>
                     <Card.Content extra>
-                        <b>Status</b>: {status==0?"UnderRaising":status==1?"Success":"Failure"}
+                        <b>Status</b>: {status==0?"UnderRaising":status==1?"Success":status==3?"Dissolved":"Failure"}
                     </Card.Content>
                 </Card>;
     }
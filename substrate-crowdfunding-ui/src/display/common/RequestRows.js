@@ -92,7 +92,7 @@ class RequestRow extends ReactiveComponent{
                 <Pretty value={supportCount}/>/<Pretty value={totalCount}/>
             </Table.Cell>
             <Table.Cell>
-                {status==0?"Under Voting":status==1?"Success":"Failure"}
+                {status==0?"Under Voting":status==1?"Success":status==3?"Cancelled":status==4?"Approved":status==5?"In Dispute":"Failure"}
             </Table.Cell>
             <Table.Cell>
                 <div style={{ paddingBottom: '1em' }}>
@@ -108,7 +108,7 @@ class RequestRow extends ReactiveComponent{
                 </div>
             </Table.Cell>
             <Table.Cell>
-                <If condition={status!=1} then={
+                <If condition={status==0} then={
                     <TransactButton
                         content="Support"
                         icon='send'
@@ -59,6 +59,8 @@ class CreateFunding extends ReactiveComponent{
         this.targetAmount = new Bond;
         this.supportAmount = new Bond;
         this.expiry = new Bond;
+        this.allowance = new Bond;
+        this.allowancePeriod = new Bond;
     }
 
 
@@ -96,6 +98,19 @@ class CreateFunding extends ReactiveComponent{
                     validator={n => n || null}
                 />
             </div>
+            <div style={{ paddingBottom: '1em' }}>
+                <div style={{ fontSize: 'small' }}>Allowance Without Voting</div>
+                <BalanceBond bond={this.allowance} />
+            </div>
+            <div style={{ paddingBottom: '1em' }}>
+                <div style={{ fontSize: 'small' }}>Allowance Period</div>
+                <InputBond
+                    bond={this.allowancePeriod}
+                    placeholder='Blocks per allowance period'
+                    defaultValue='0'
+                    validator={n => n || null}
+                />
+            </div>
             <div style={{ paddingBottom: '1em' }}>
                 <div style={{ fontSize: 'small' }}>Account</div>
                 <SignerBond bond={this.skAccount} />
@@ -117,7 +132,7 @@ class CreateFunding extends ReactiveComponent{
                 icon='send'
                 tx={{
                     sender: runtime.indices.tryIndex(this.skAccount),
-                    call: calls.fundingFactory.createFunding(this.projectName.map(stringToBytes), this.targetAmount, this.supportAmount, this.expiry),
+                    call: calls.fundingFactory.createFunding(this.projectName.map(stringToBytes), this.targetAmount, this.supportAmount, this.expiry, this.allowance, this.allowancePeriod, [], null, []),
                     compact: false,
                     longevity: true
                 }}
@@ -134,6 +149,7 @@ class CreateRequest extends ReactiveComponent{
         this.purpose = new Bond;
         this.cost = new Bond;
         this.expiry = new Bond;
+        this.beneficiary = new Bond;
     }
 
 
@@ -167,6 +183,10 @@ class CreateRequest extends ReactiveComponent{
                 <div style={{ fontSize: 'small' }}>Cost</div>
                 <BalanceBond bond={this.cost} />
             </div>
+            <div style={{ paddingBottom: '1em' }}>
+                <div style={{ fontSize: 'small' }}>Beneficiary</div>
+                <AccountIdBond bond={this.beneficiary} />
+            </div>
             <div style={{ paddingBottom: '1em' }}>
                 <div style={{ fontSize: 'small' }}>Expiry</div>
                 <InputBond
@@ -196,7 +216,7 @@ class CreateRequest extends ReactiveComponent{
                 icon='send'
                 tx={{
                     sender: runtime.indices.tryIndex(this.skAccount),
-                    call: calls.request.createRequest(this.fundingId, this.purpose.map(stringToBytes), this.cost, this.expiry),
+                    call: calls.request.createRequest(this.fundingId, this.purpose.map(stringToBytes), this.cost, 0, this.expiry, Bond.all([this.beneficiary, this.cost]).map(([who, cost]) => [[who, cost]]), null),
                     compact: false,
                     longevity: true
                 }}
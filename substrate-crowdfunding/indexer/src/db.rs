@@ -0,0 +1,381 @@
+//! The SQLite database of the indexer.
+//!
+//! Every row records the block that added it, so the rows of the blocks that leave the best
+//! chain are rolled back by deleting them. The balances are `u128` and stored as decimal text.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, NO_PARAMS};
+use substrate_crowdfunding_runtime::{BlockNumber, Hash};
+
+use crate::errors::Result;
+use crate::events::{self, Record, Row};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    finalized INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS fundings (
+    funding_id TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    extrinsic INTEGER,
+    manager TEXT NOT NULL,
+    project_name TEXT NOT NULL,
+    target_money TEXT NOT NULL,
+    support_money TEXT NOT NULL,
+    expiry INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS investments (
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    extrinsic INTEGER,
+    funding_id TEXT NOT NULL,
+    investor TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    PRIMARY KEY (block_number, event_index)
+);
+CREATE INDEX IF NOT EXISTS investments_by_investor ON investments (investor);
+CREATE INDEX IF NOT EXISTS investments_by_funding ON investments (funding_id);
+CREATE TABLE IF NOT EXISTS requests (
+    request_id TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    extrinsic INTEGER,
+    funding_id TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    cost TEXT NOT NULL,
+    expiry INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS requests_by_funding ON requests (funding_id);
+CREATE TABLE IF NOT EXISTS votes (
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    extrinsic INTEGER,
+    request_id TEXT NOT NULL,
+    voter TEXT NOT NULL,
+    approve INTEGER NOT NULL,
+    PRIMARY KEY (block_number, event_index)
+);
+CREATE INDEX IF NOT EXISTS votes_by_request ON votes (request_id);
+CREATE TABLE IF NOT EXISTS settlements (
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    extrinsic INTEGER,
+    kind TEXT NOT NULL,
+    funding_id TEXT,
+    request_id TEXT,
+    account TEXT,
+    amount TEXT,
+    success INTEGER,
+    PRIMARY KEY (block_number, event_index)
+);
+CREATE INDEX IF NOT EXISTS settlements_by_funding ON settlements (funding_id);
+CREATE INDEX IF NOT EXISTS settlements_by_request ON settlements (request_id);
+";
+
+// The tables whose rows are deleted when their block is rolled back
+const TABLES: [&str; 6] = ["blocks", "fundings", "investments", "requests", "votes", "settlements"];
+
+/// A block with its decoded events.
+pub struct Block {
+    pub number: BlockNumber,
+    pub hash: Hash,
+    pub timestamp: u64,
+    pub records: Vec<Record>,
+}
+
+/// The database of the indexer.
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    /// Open the database at the path, creating the tables that do not exist.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Db { conn })
+    }
+
+    /// The last indexed block.
+    pub fn last_block(&self) -> Result<Option<(BlockNumber, Hash)>> {
+        let last = self
+            .conn
+            .query_row(
+                "SELECT number, hash FROM blocks ORDER BY number DESC LIMIT 1",
+                NO_PARAMS,
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        match last {
+            Some((number, hash)) => Ok(Some((number as BlockNumber, parse_hash(&hash)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// The hash of the indexed block at the number.
+    pub fn block_hash(&self, number: BlockNumber) -> Result<Option<Hash>> {
+        let hash = self
+            .conn
+            .query_row(
+                "SELECT hash FROM blocks WHERE number = ?1",
+                params![number as i64],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        match hash {
+            Some(hash) => Ok(Some(parse_hash(&hash)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Judge if the indexed block at the number is finalized.
+    pub fn is_finalized(&self, number: BlockNumber) -> Result<bool> {
+        let finalized = self
+            .conn
+            .query_row(
+                "SELECT finalized FROM blocks WHERE number = ?1",
+                params![number as i64],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()?;
+        Ok(finalized.unwrap_or(false))
+    }
+
+    /// Write the block and the rows of its events, all or nothing.
+    pub fn insert_block(&mut self, block: &Block) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let number = block.number as i64;
+        tx.execute(
+            "INSERT INTO blocks (number, hash, timestamp) VALUES (?1, ?2, ?3)",
+            params![number, events::hash(&block.hash), block.timestamp as i64],
+        )?;
+        for record in &block.records {
+            let index = record.index as i64;
+            let extrinsic = record.extrinsic.map(|extrinsic| extrinsic as i64);
+            match &record.row {
+                Row::Funding {
+                    funding_id,
+                    manager,
+                    project_name,
+                    target_money,
+                    support_money,
+                    expiry,
+                } => tx.execute(
+                    "INSERT INTO fundings (funding_id, block_number, event_index, extrinsic, manager,
+                        project_name, target_money, support_money, expiry)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        events::hash(funding_id),
+                        number,
+                        index,
+                        extrinsic,
+                        events::account(manager),
+                        String::from_utf8_lossy(project_name).into_owned(),
+                        target_money.to_string(),
+                        support_money.to_string(),
+                        *expiry as i64,
+                    ],
+                )?,
+                Row::Investment {
+                    funding_id,
+                    investor,
+                    amount,
+                } => tx.execute(
+                    "INSERT INTO investments (block_number, event_index, extrinsic, funding_id, investor, amount)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        number,
+                        index,
+                        extrinsic,
+                        events::hash(funding_id),
+                        events::account(investor),
+                        amount.to_string(),
+                    ],
+                )?,
+                Row::Request {
+                    request_id,
+                    funding_id,
+                    owner,
+                    cost,
+                    expiry,
+                } => tx.execute(
+                    "INSERT INTO requests (request_id, block_number, event_index, extrinsic, funding_id,
+                        owner, cost, expiry)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        events::hash(request_id),
+                        number,
+                        index,
+                        extrinsic,
+                        events::hash(funding_id),
+                        events::account(owner),
+                        cost.to_string(),
+                        *expiry as i64,
+                    ],
+                )?,
+                Row::Vote {
+                    request_id,
+                    voter,
+                    approve,
+                } => tx.execute(
+                    "INSERT INTO votes (block_number, event_index, extrinsic, request_id, voter, approve)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        number,
+                        index,
+                        extrinsic,
+                        events::hash(request_id),
+                        events::account(voter),
+                        approve,
+                    ],
+                )?,
+                Row::Settlement {
+                    kind,
+                    funding_id,
+                    request_id,
+                    account,
+                    amount,
+                    success,
+                } => tx.execute(
+                    "INSERT INTO settlements (block_number, event_index, extrinsic, kind, funding_id,
+                        request_id, account, amount, success)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        number,
+                        index,
+                        extrinsic,
+                        kind,
+                        funding_id.as_ref().map(events::hash),
+                        request_id.as_ref().map(events::hash),
+                        account.as_ref().map(events::account),
+                        amount.map(|amount| amount.to_string()),
+                        success,
+                    ],
+                )?,
+            };
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete the blocks from the number on and the rows of their events, all or nothing.
+    pub fn rollback_from(&mut self, number: BlockNumber) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for table in TABLES.iter() {
+            let column = if *table == "blocks" { "number" } else { "block_number" };
+            tx.execute(
+                &format!("DELETE FROM {} WHERE {} >= ?1", table, column),
+                params![number as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Mark the blocks until the number as finalized, they are never rolled back.
+    pub fn finalize_until(&self, number: BlockNumber) -> Result<()> {
+        self.conn.execute(
+            "UPDATE blocks SET finalized = 1 WHERE number <= ?1 AND finalized = 0",
+            params![number as i64],
+        )?;
+        Ok(())
+    }
+}
+
+fn parse_hash(hash: &str) -> Result<Hash> {
+    let bytes = hex::decode(hash.trim_start_matches("0x"))?;
+    if bytes.len() != 32 {
+        return Err(format!("Invalid hash {} in the database", hash).into());
+    }
+    Ok(Hash::from_slice(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substrate_crowdfunding_runtime::AccountId;
+
+    fn memory_db() -> Db {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        Db { conn }
+    }
+
+    // A block of the number with a vote on the request, the seed tells the forks apart
+    fn block(number: BlockNumber, seed: u8) -> Block {
+        Block {
+            number,
+            hash: Hash::repeat_byte(seed),
+            timestamp: number * 6000,
+            records: vec![Record {
+                extrinsic: Some(1),
+                index: 0,
+                row: Row::Vote {
+                    request_id: Hash::repeat_byte(0xff),
+                    voter: AccountId::from_raw([seed; 32]),
+                    approve: true,
+                },
+            }],
+        }
+    }
+
+    fn votes(db: &Db) -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM votes", NO_PARAMS, |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn insert_block_should_write_the_rows() {
+        let mut db = memory_db();
+        assert_eq!(db.last_block().unwrap(), None);
+
+        db.insert_block(&block(0, 1)).unwrap();
+        db.insert_block(&block(1, 2)).unwrap();
+        assert_eq!(db.last_block().unwrap(), Some((1, Hash::repeat_byte(2))));
+        assert_eq!(db.block_hash(0).unwrap(), Some(Hash::repeat_byte(1)));
+        assert_eq!(votes(&db), 2);
+
+        // a block is written only once
+        assert!(db.insert_block(&block(1, 3)).is_err());
+        assert_eq!(votes(&db), 2);
+    }
+
+    #[test]
+    fn rollback_from_should_delete_the_later_blocks() {
+        let mut db = memory_db();
+        for number in 0..3 {
+            db.insert_block(&block(number, 1)).unwrap();
+        }
+
+        db.rollback_from(1).unwrap();
+        assert_eq!(db.last_block().unwrap(), Some((0, Hash::repeat_byte(1))));
+        assert_eq!(db.block_hash(1).unwrap(), None);
+        assert_eq!(votes(&db), 1);
+    }
+
+    #[test]
+    fn resume_should_continue_on_the_new_fork() {
+        let mut db = memory_db();
+        for number in 0..3 {
+            db.insert_block(&block(number, 1)).unwrap();
+        }
+        db.finalize_until(0).unwrap();
+        assert!(db.is_finalized(0).unwrap());
+        assert!(!db.is_finalized(1).unwrap());
+
+        // the fork replaces the blocks after the finalized one
+        db.rollback_from(1).unwrap();
+        let next = db.last_block().unwrap().map_or(0, |(number, _)| number + 1);
+        assert_eq!(next, 1);
+        db.insert_block(&block(next, 2)).unwrap();
+        assert_eq!(db.last_block().unwrap(), Some((1, Hash::repeat_byte(2))));
+        assert!(db.is_finalized(0).unwrap());
+        assert_eq!(votes(&db), 2);
+    }
+}
@@ -0,0 +1,21 @@
+//! Indexer errors.
+
+use error_chain::error_chain;
+
+error_chain! {
+	foreign_links {
+		Io(::std::io::Error) #[doc="IO error"];
+		Hex(::hex::FromHexError) #[doc="Hex decoding error"];
+		Http(::reqwest::Error) #[doc="Node connection error"];
+		Json(::serde_json::Error) #[doc="JSON-RPC response error"];
+		Sqlite(::rusqlite::Error) #[doc="Database error"];
+	}
+
+	errors {
+		/// The node returned data that the runtime of the indexer can't decode.
+		Decode(what: String) {
+			description("Decoding error"),
+			display("Invalid {} returned by the node, the runtime of the indexer may be outdated", what),
+		}
+	}
+}
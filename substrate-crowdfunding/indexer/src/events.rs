@@ -0,0 +1,223 @@
+//! Decode the `funding_factory` and `request` events of a block into the rows of the tables.
+
+use primitives::crypto::Ss58Codec;
+use substrate_crowdfunding_runtime::{
+    funding_factory, request, AccountId, Event, EventRecords, Hash, Phase, Runtime, SystemEvent,
+};
+
+use crate::errors::Result;
+use crate::node::Node;
+
+/// A row that an event adds to a table.
+pub enum Row {
+    Funding {
+        funding_id: Hash,
+        manager: AccountId,
+        project_name: Vec<u8>,
+        target_money: u128,
+        support_money: u128,
+        expiry: u64,
+    },
+    Investment {
+        funding_id: Hash,
+        investor: AccountId,
+        amount: u128,
+    },
+    Request {
+        request_id: Hash,
+        funding_id: Hash,
+        owner: AccountId,
+        cost: u128,
+        expiry: u64,
+    },
+    Vote {
+        request_id: Hash,
+        voter: AccountId,
+        approve: bool,
+    },
+    Settlement {
+        kind: &'static str,
+        funding_id: Option<Hash>,
+        request_id: Option<Hash>,
+        account: Option<AccountId>,
+        amount: Option<u128>,
+        success: Option<bool>,
+    },
+}
+
+/// A decoded event with its position in the block.
+pub struct Record {
+    /// The index of the extrinsic, `None` for the events of `on_initialize` and `on_finalize`.
+    pub extrinsic: Option<u32>,
+    /// The index of the event in the block.
+    pub index: u32,
+    pub row: Row,
+}
+
+/// Decode the events of the block at the hash, the name of a created funding is read from the node.
+///
+/// The events of a failed extrinsic are skipped, they describe a call that did not complete.
+pub fn decode(node: &Node, at: Hash, events: EventRecords) -> Result<Vec<Record>> {
+    let failed: Vec<Phase> = events
+        .iter()
+        .filter(|record| match record.event {
+            Event::system(SystemEvent::ExtrinsicFailed) => true,
+            _ => false,
+        })
+        .map(|record| record.phase.clone())
+        .collect();
+    let mut records = Vec::new();
+    for (index, record) in events.into_iter().enumerate() {
+        if failed.contains(&record.phase) {
+            continue;
+        }
+        let row = match record.event {
+            Event::funding_factory(event) => match funding_factory_row(node, at, event)? {
+                Some(row) => row,
+                None => continue,
+            },
+            Event::request(event) => match request_row(event) {
+                Some(row) => row,
+                None => continue,
+            },
+            _ => continue,
+        };
+        let extrinsic = match record.phase {
+            Phase::ApplyExtrinsic(extrinsic) => Some(extrinsic),
+            _ => None,
+        };
+        records.push(Record {
+            extrinsic,
+            index: index as u32,
+            row,
+        });
+    }
+    Ok(records)
+}
+
+fn funding_factory_row(
+    node: &Node,
+    at: Hash,
+    event: funding_factory::Event<Runtime>,
+) -> Result<Option<Row>> {
+    use funding_factory::RawEvent;
+
+    Ok(Some(match event {
+        RawEvent::CreateFunding(manager, funding_id, target_money, support_money, expiry) => {
+            let project_name = node
+                .funding_details(funding_id, at)?
+                .map(|details| details.project_name)
+                .unwrap_or_default();
+            Row::Funding {
+                funding_id,
+                manager,
+                project_name,
+                target_money,
+                support_money,
+                expiry,
+            }
+        }
+        RawEvent::Invest(funding_id, investor, amount) => Row::Investment {
+            funding_id,
+            investor,
+            amount,
+        },
+        RawEvent::FundingFinalized(funding_id, amount, _, success) => Row::Settlement {
+            kind: "funding_finalized",
+            funding_id: Some(funding_id),
+            request_id: None,
+            account: None,
+            amount: Some(amount),
+            success: Some(success),
+        },
+        RawEvent::ListingFeePaid(funding_id, manager, fee) => Row::Settlement {
+            kind: "listing_fee",
+            funding_id: Some(funding_id),
+            request_id: None,
+            account: Some(manager),
+            amount: Some(fee),
+            success: None,
+        },
+        RawEvent::SuccessFeePaid(funding_id, fee) => Row::Settlement {
+            kind: "success_fee",
+            funding_id: Some(funding_id),
+            request_id: None,
+            account: None,
+            amount: Some(fee),
+            success: None,
+        },
+        _ => return Ok(None),
+    }))
+}
+
+fn request_row(event: request::Event<Runtime>) -> Option<Row> {
+    use request::RawEvent;
+
+    Some(match event {
+        RawEvent::CreateRequest(owner, funding_id, request_id, cost, expiry) => Row::Request {
+            request_id,
+            funding_id,
+            owner,
+            cost,
+            expiry,
+        },
+        RawEvent::Vote(voter, request_id) => Row::Vote {
+            request_id,
+            voter,
+            approve: true,
+        },
+        RawEvent::RejectVote(voter, request_id) => Row::Vote {
+            request_id,
+            voter,
+            approve: false,
+        },
+        RawEvent::VoteRevealed(voter, request_id, approve) => Row::Vote {
+            request_id,
+            voter,
+            approve,
+        },
+        RawEvent::RequestFinalized(request_id, _, _, success, _) => Row::Settlement {
+            kind: "request_finalized",
+            funding_id: None,
+            request_id: Some(request_id),
+            account: None,
+            amount: None,
+            success: Some(success),
+        },
+        RawEvent::Payout(request_id, beneficiary, amount) => Row::Settlement {
+            kind: "payout",
+            funding_id: None,
+            request_id: Some(request_id),
+            account: Some(beneficiary),
+            amount: Some(amount),
+            success: None,
+        },
+        RawEvent::Refund(funding_id, investor, amount) => Row::Settlement {
+            kind: "refund",
+            funding_id: Some(funding_id),
+            request_id: None,
+            account: Some(investor),
+            amount: Some(amount),
+            success: None,
+        },
+        RawEvent::FundingDissolved(funding_id, remain) => Row::Settlement {
+            kind: "dissolved",
+            funding_id: Some(funding_id),
+            request_id: None,
+            account: None,
+            amount: Some(remain),
+            success: None,
+        },
+        _ => return None,
+    })
+}
+
+/// The text stored for an account.
+pub fn account(account: &AccountId) -> String {
+    account.to_ss58check()
+}
+
+/// The text stored for a hash.
+pub fn hash(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
@@ -0,0 +1,111 @@
+//! Crowdfunding indexer, following a node over its JSON-RPC and writing the `funding_factory` and
+//! `request` events to normalized SQLite tables for the historical queries.
+//!
+//! The indexer resumes after the last block in the database. A block that is no longer in the best
+//! chain is rolled back with the blocks after it, unless the node has finalized it. The node runs
+//! Aura without a finality gadget, so a block is rolled back however deep the reorg goes until the
+//! node reports it as finalized.
+
+#![warn(unused_extern_crates)]
+
+mod db;
+mod errors;
+mod events;
+mod node;
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use structopt::StructOpt;
+
+use crate::db::{Block, Db};
+use crate::errors::{Error, ErrorKind, Result};
+use crate::node::Node;
+
+/// The parameters of the indexer.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "substrate-crowdfunding-indexer")]
+struct Params {
+    /// Specify the HTTP JSON-RPC url of the node
+    #[structopt(long = "node-url", value_name = "URL", default_value = "http://127.0.0.1:9933")]
+    node_url: String,
+
+    /// Specify the SQLite database file
+    #[structopt(
+        long = "database",
+        value_name = "PATH",
+        default_value = "crowdfunding-index.sqlite",
+        parse(from_os_str)
+    )]
+    database: PathBuf,
+
+    /// Specify the seconds to wait for new blocks once the indexer caught up with the node
+    #[structopt(long = "poll-interval", value_name = "SECONDS", default_value = "3")]
+    poll_interval: u64,
+}
+
+fn run() -> Result<()> {
+    env_logger::init();
+    let params = Params::from_args();
+    info!("Indexing {} into {}", params.node_url, params.database.display());
+    let mut db = Db::open(&params.database)?;
+    let node = Node::new(params.node_url);
+
+    loop {
+        match sync(&node, &mut db) {
+            // retrying can't decode the data, stop instead of skipping the block
+            Err(e @ Error(ErrorKind::Decode(_), _)) => return Err(e),
+            // the node may be restarting, retry at the next poll
+            Err(e) => warn!("Error following the node: {}", e),
+            Ok(()) => (),
+        }
+        thread::sleep(Duration::from_secs(params.poll_interval));
+    }
+}
+
+// Roll back the blocks that left the best chain and index the new ones
+fn sync(node: &Node, db: &mut Db) -> Result<()> {
+    rollback_forks(node, db)?;
+
+    let best_number = node.header(None)?.number;
+    let mut number = db.last_block()?.map_or(0, |(number, _)| number + 1);
+    while number <= best_number {
+        let hash = match node.block_hash(number)? {
+            Some(hash) => hash,
+            None => break,
+        };
+        // the best chain changed since the rollback, the next sync rolls it back
+        if number > 0 && db.block_hash(number - 1)? != Some(node.header(Some(hash))?.parent_hash) {
+            break;
+        }
+        let records = events::decode(node, hash, node.events(hash)?)?;
+        db.insert_block(&Block {
+            number,
+            hash,
+            timestamp: node.timestamp(hash)?,
+            records,
+        })?;
+        number += 1;
+    }
+
+    db.finalize_until(node.finalized_number()?)
+}
+
+// Walk back from the last indexed block until a block still in the best chain
+fn rollback_forks(node: &Node, db: &mut Db) -> Result<()> {
+    while let Some((number, hash)) = db.last_block()? {
+        if node.block_hash(number)? == Some(hash) {
+            break;
+        }
+        if db.is_finalized(number)? {
+            return Err(format!("The finalized block {} left the best chain", number).into());
+        }
+        info!("Rolling back the block {} ({:?})", number, hash);
+        db.rollback_from(number)?;
+    }
+    Ok(())
+}
+
+error_chain::quick_main!(run);
@@ -0,0 +1,116 @@
+//! A client of the standard `chain_*` and `state_*` JSON-RPC methods of the node.
+
+use parity_codec::{Decode, Encode};
+use primitives::twox_128;
+use serde_json::{json, Value};
+use substrate_crowdfunding_runtime::{BlockNumber, EventRecords, FundingDetails, Hash};
+
+use crate::errors::{ErrorKind, Result};
+
+/// The header fields that the indexer needs.
+pub struct Header {
+    pub number: BlockNumber,
+    pub parent_hash: Hash,
+}
+
+/// The node followed by the indexer.
+pub struct Node {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl Node {
+    /// Connect to the HTTP JSON-RPC server of the node at the url.
+    pub fn new(url: String) -> Self {
+        Node {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let mut response: Value = self.client.post(&self.url).json(&request).send()?.json()?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed: {}", method, error).into());
+        }
+        Ok(response["result"].take())
+    }
+
+    /// The hash of the block at the number in the best chain, `None` if there is no such block yet.
+    pub fn block_hash(&self, number: BlockNumber) -> Result<Option<Hash>> {
+        let hash = self.call("chain_getBlockHash", json!([number]))?;
+        Ok(serde_json::from_value(hash)?)
+    }
+
+    /// The header of the block, the best block if `at` is `None`.
+    pub fn header(&self, at: Option<Hash>) -> Result<Header> {
+        let header = self.call("chain_getHeader", json!([at]))?;
+        let number = match &header["number"] {
+            Value::String(hex) => BlockNumber::from_str_radix(hex.trim_start_matches("0x"), 16)
+                .map_err(|e| format!("Invalid block number {}: {}", hex, e))?,
+            number => serde_json::from_value(number.clone())?,
+        };
+        Ok(Header {
+            number,
+            parent_hash: serde_json::from_value(header["parentHash"].clone())?,
+        })
+    }
+
+    /// The number of the last finalized block.
+    pub fn finalized_number(&self) -> Result<BlockNumber> {
+        let hash = serde_json::from_value(self.call("chain_getFinalizedHead", json!([]))?)?;
+        Ok(self.header(Some(hash))?.number)
+    }
+
+    fn storage(&self, key: &[u8], at: Hash) -> Result<Option<Vec<u8>>> {
+        let data = self.call("state_getStorage", json!([bytes(key), at]))?;
+        match data {
+            Value::String(hex) => Ok(Some(hex::decode(hex.trim_start_matches("0x"))?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The events deposited in the block.
+    pub fn events(&self, at: Hash) -> Result<EventRecords> {
+        match self.storage(&twox_128(b"System Events"), at)? {
+            Some(data) => decode(&data, "events"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The unix time of the block in seconds.
+    pub fn timestamp(&self, at: Hash) -> Result<u64> {
+        match self.storage(&twox_128(b"Timestamp Now"), at)? {
+            Some(data) => decode(&data, "timestamp"),
+            None => Ok(0),
+        }
+    }
+
+    /// The funding after the block, read through the crowdfunding runtime API.
+    pub fn funding_details(&self, funding_id: Hash, at: Hash) -> Result<Option<FundingDetails>> {
+        let data = self.call(
+            "state_call",
+            json!(["CrowdfundingApi_funding_details", bytes(&funding_id.encode()), at]),
+        )?;
+        let data = match data {
+            Value::String(hex) => hex::decode(hex.trim_start_matches("0x"))?,
+            _ => return Ok(None),
+        };
+        decode(&data, "funding details")
+    }
+}
+
+// Decode the data returned by the node, failing instead of indexing wrong rows
+fn decode<T: Decode>(data: &[u8], what: &str) -> Result<T> {
+    Decode::decode(&mut &data[..]).ok_or_else(|| ErrorKind::Decode(what.into()).into())
+}
+
+fn bytes(data: &[u8]) -> String {
+    format!("0x{}", hex::encode(data))
+}
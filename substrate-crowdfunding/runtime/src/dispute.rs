@@ -0,0 +1,441 @@
+use crate::{funding_factory, request};
+use support::{decl_storage, decl_module, StorageValue, StorageMap,
+              dispatch::Result, ensure, decl_event, traits::ReservableCurrency};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::traits::{As, Hash, Zero};
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Dispute<Hash, AccountId, Balance, BlockNumber>{
+    // the only id of a dispute
+    dispute_id: Hash,
+    // the request or the funding in dispute
+    target: Hash,
+    // the funding of the target
+    funding_id: Hash,
+    // kind 0- Request 1- Funding
+    kind: u64,
+    // the investor who raised the dispute
+    challenger: AccountId,
+    // the bond of the challenger
+    bond: Balance,
+    // the bond of the manager, zero if the manager does not defend
+    manager_bond: Balance,
+    // the arbitration deadline
+    expiry: BlockNumber,
+    // verdict 0- Undecided 1- Release 2- Reject 3- Dissolve 4- Expired undecided
+    verdict: u64,
+}
+
+decl_error! {
+    /// The errors of the module, the codes are stable and must never be reused
+    pub enum DisputeError {
+        RequestNotExist = 300 => "The request does not exist",
+        RequestNotDisputable = 301 => "The request can not be disputed",
+        FundingNotExist = 302 => "The funding does not exist",
+        UnknownKind = 303 => "Unknown kind of dispute",
+        FundingNotSucceed = 304 => "The funding does not succeed",
+        DisputeOpen = 305 => "The dispute already exists",
+        NotInvestor = 306 => "You are not the investor",
+        StakeNotEnough = 307 => "Your stake is not enough to raise a dispute",
+        DisputeExists = 308 => "Dispute already exists",
+        DisputeNotExist = 309 => "The dispute does not exist",
+        DisputeOver = 310 => "The dispute is over",
+        DisputeDefended = 311 => "The dispute has been defended",
+        OwnerNotExist = 312 => "The owner does not exist",
+        NotFundingOwner = 313 => "The sender must be the owner of the funding",
+        NotArbitrator = 314 => "You are not the arbitrator",
+        UnknownVerdict = 315 => "Unknown verdict",
+        VotedBefore = 316 => "You have voted before",
+        VerdictCountOverflow = 317 => "Overflow adding the number of arbitrators who have voted the verdict",
+    }
+}
+
+pub trait Trait: request::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+decl_event!(
+    pub enum Event<T>
+    where
+        <T as system::Trait>::AccountId,
+        <T as system::Trait>::Hash,
+        <T as balances::Trait>::Balance,
+        <T as system::Trait>::BlockNumber
+    {
+        RaiseDispute(AccountId, Hash, Hash, Balance, BlockNumber),
+        DefendDispute(AccountId, Hash, Balance),
+        Arbitrate(AccountId, Hash, u64),
+        DisputeResolved(Hash, u64),
+        BondSlashed(Hash, AccountId, Balance),
+        ArbitratorsChanged(Vec<AccountId>),
+        /// The expired dispute could not be resolved and stays open
+        DisputeResolutionFailed(Hash),
+        /// A call failed with the module error, which is encoded as its stable code
+        CallFailed(DisputeError),
+    }
+);
+
+decl_storage! {
+    trait Store for Module<T: Trait> as FundingDispute {
+        // Global state
+        Disputes get(disputes): map T::Hash => Dispute<T::Hash, T::AccountId, T::Balance, T::BlockNumber>;
+        // The open dispute of a request or a funding
+        DisputeOf get(dispute_of): map T::Hash => Option<T::Hash>;
+
+        // The council who decides the disputes
+        Arbitrators get(arbitrators) config(): Vec<T::AccountId>;
+        // The bond that the challenger and the manager post
+        DisputeBond get(dispute_bond) config(): T::Balance = T::Balance::sa(1000);
+        // The minimum money that the challenger has invested in the funding
+        MinDisputeStake get(min_dispute_stake) config(): T::Balance = T::Balance::sa(1000);
+        // Maximum time for the arbitrators to decide
+        DisputePeriod get(dispute_period) config(): T::BlockNumber = T::BlockNumber::sa(60480);
+
+        // Disputes ending in a block
+        DisputesByBlockNumber get(dispute_expire_at): map T::BlockNumber => Vec<T::Hash>;
+
+        // The verdict that the arbitrator voted
+        ArbitrationVote get(arbitration_vote_of): map (T::Hash, T::AccountId) => u64;
+        // The number of arbitrators who voted the verdict
+        VerdictCount get(verdict_count): map (T::Hash, u64) => u64;
+
+        // Record the number of disputes
+        Nonce: u64;
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        // Initializing events
+        fn deposit_event<T>() = default;
+
+        /// Escalate a request or a funding to the arbitrators by posting a bond
+        fn raise_dispute(origin, target: T::Hash, kind: u64) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+
+                // Get the funding of the target and ensure the target can be disputed
+                let funding_id = match kind {
+                    0 => {
+                        let funding_id = <request::Module<T>>::get_request_funding(target).ok_or_else(|| Self::failed(DisputeError::RequestNotExist))?;
+                        let status = <request::Module<T>>::get_request_status(target);
+                        ensure!(status == 0 || status == 4, Self::failed(DisputeError::RequestNotDisputable));
+                        funding_id
+                    },
+                    1 => {
+                        ensure!(<funding_factory::Module<T>>::is_funding_exists(target), Self::failed(DisputeError::FundingNotExist));
+                        target
+                    },
+                    _ => return Err(Self::failed(DisputeError::UnknownKind)),
+                };
+                ensure!(<funding_factory::Module<T>>::is_funding_success(funding_id) == 1, Self::failed(DisputeError::FundingNotSucceed));
+                ensure!(Self::dispute_of(&target).is_none(), Self::failed(DisputeError::DisputeOpen));
+
+                // Ensure the challenger holds enough stake
+                let stake = <funding_factory::Module<T>>::get_invest_amount(funding_id, sender.clone());
+                ensure!(stake > T::Balance::zero(), Self::failed(DisputeError::NotInvestor));
+                ensure!(stake >= Self::min_dispute_stake(), Self::failed(DisputeError::StakeNotEnough));
+
+                let expiry = <system::Module<T>>::block_number() + Self::dispute_period();
+                let nonce = <Nonce<T>>::get();
+                let dispute_id = (<system::Module<T>>::random_seed(), &sender, &target, nonce)
+                    .using_encoded(<T as system::Trait>::Hashing::hash);
+                ensure!(!<Disputes<T>>::exists(&dispute_id), Self::failed(DisputeError::DisputeExists));
+
+                // Post the bond
+                let bond = Self::dispute_bond();
+                <balances::Module<T>>::reserve(&sender, bond.clone())?;
+
+                // Hold the request or the money of the funding until the dispute is resolved
+                if kind == 0 {
+                    <request::Module<T>>::set_request_disputed(target);
+                } else {
+                    <request::Module<T>>::set_funding_disputed(target, true);
+                }
+
+                <Disputes<T>>::insert(dispute_id.clone(), Dispute{
+                    dispute_id: dispute_id.clone(),
+                    target: target.clone(),
+                    funding_id,
+                    kind,
+                    challenger: sender.clone(),
+                    bond: bond.clone(),
+                    manager_bond: T::Balance::zero(),
+                    expiry: expiry.clone(),
+                    verdict: 0,
+                });
+                <DisputeOf<T>>::insert(target.clone(), dispute_id.clone());
+                <DisputesByBlockNumber<T>>::mutate(expiry.clone(), |disputes| disputes.push(dispute_id.clone()));
+                <Nonce<T>>::mutate(|n| *n += 1);
+
+                Self::deposit_event(RawEvent::RaiseDispute(sender, dispute_id, target, bond, expiry));
+                Ok(())
+            })
+        }
+
+        /// The manager of the funding posts the same bond to defend the dispute
+        fn defend_dispute(origin, dispute_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                ensure!(<Disputes<T>>::exists(&dispute_id), Self::failed(DisputeError::DisputeNotExist));
+                let mut dispute = Self::disputes(&dispute_id);
+                ensure!(dispute.verdict == 0, Self::failed(DisputeError::DisputeOver));
+                ensure!(dispute.manager_bond == T::Balance::zero(), Self::failed(DisputeError::DisputeDefended));
+                let owner = <funding_factory::Module<T>>::get_funding_owner(dispute.funding_id).ok_or_else(|| Self::failed(DisputeError::OwnerNotExist))?;
+                ensure!(sender == owner, Self::failed(DisputeError::NotFundingOwner));
+
+                // Post the bond
+                <balances::Module<T>>::reserve(&sender, dispute.bond.clone())?;
+                dispute.manager_bond = dispute.bond.clone();
+                <Disputes<T>>::insert(dispute_id.clone(), dispute.clone());
+
+                Self::deposit_event(RawEvent::DefendDispute(sender, dispute_id, dispute.manager_bond));
+                Ok(())
+            })
+        }
+
+        /// Vote a verdict on the dispute: 1- Release 2- Reject 3- Dissolve
+        fn arbitrate(origin, dispute_id: T::Hash, verdict: u64) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                let arbitrators = Self::arbitrators();
+                ensure!(arbitrators.contains(&sender), Self::failed(DisputeError::NotArbitrator));
+                ensure!(verdict >= 1 && verdict <= 3, Self::failed(DisputeError::UnknownVerdict));
+                ensure!(<Disputes<T>>::exists(&dispute_id), Self::failed(DisputeError::DisputeNotExist));
+                let dispute = Self::disputes(&dispute_id);
+                ensure!(dispute.verdict == 0, Self::failed(DisputeError::DisputeOver));
+                ensure!(Self::arbitration_vote_of((dispute_id.clone(), sender.clone())) == 0, Self::failed(DisputeError::VotedBefore));
+
+                let verdict_count = Self::verdict_count((dispute_id.clone(), verdict));
+                let new_verdict_count = verdict_count.checked_add(1).ok_or_else(|| Self::failed(DisputeError::VerdictCountOverflow))?;
+
+                // If the verdict gets more than half of the arbitrators, the dispute is resolved,
+                // the vote is not recorded if the verdict can not be applied
+                if new_verdict_count > arbitrators.len() as u64 / 2 {
+                    Self::resolve(dispute_id.clone(), verdict)?;
+                }
+                <ArbitrationVote<T>>::insert((dispute_id.clone(), sender.clone()), verdict);
+                <VerdictCount<T>>::insert((dispute_id.clone(), verdict), new_verdict_count);
+                Self::deposit_event(RawEvent::Arbitrate(sender, dispute_id, verdict));
+                Ok(())
+            })
+        }
+
+        /// Replace the arbitrator council
+        fn set_arbitrators(origin, arbitrators: Vec<T::AccountId>) -> Result{
+            Self::reported(|| {
+                ensure_root(origin)?;
+                <Arbitrators<T>>::put(arbitrators.clone());
+                Self::deposit_event(RawEvent::ArbitratorsChanged(arbitrators));
+                Ok(())
+            })
+        }
+
+        fn on_finalize() {
+            // The disputes that the arbitrators did not decide keep the request as it was and slash the challenger
+            let block_number = <system::Module<T>>::block_number();
+            for dispute_id in &Self::dispute_expire_at(block_number){
+                if Self::disputes(dispute_id).verdict == 0 {
+                    if Self::resolve(dispute_id.clone(), 4).is_err() {
+                        Self::deposit_event(RawEvent::DisputeResolutionFailed(dispute_id.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    // The message of the error, the dispatchable calls record the code for the clients
+    fn failed(error: DisputeError) -> &'static str{
+        error.as_str()
+    }
+
+    // Run the body of a dispatchable call and record the error that it failed with
+    fn reported<F: FnOnce() -> Result>(call: F) -> Result{
+        call().map_err(|message| {
+            Self::report_failure(message);
+            message
+        })
+    }
+
+    // Record the error of the module, or of the module that it calls which returned it
+    fn report_failure(message: &'static str){
+        if !Self::report(message) && !<request::Module<T>>::report(message) {
+            <funding_factory::Module<T>>::report(message);
+        }
+    }
+
+    /// Record the error of the failed call if it is an error of the module, returns false otherwise
+    pub fn report(message: &'static str) -> bool{
+        match DisputeError::from_message(message) {
+            Some(error) => {
+                Self::deposit_event(RawEvent::CallFailed(error));
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Apply the verdict to the request or the funding and settle the bonds, nothing changes if the verdict fails
+    fn resolve(dispute_id: T::Hash, verdict: u64) -> Result {
+        let mut dispute = Self::disputes(&dispute_id);
+        let owner = <funding_factory::Module<T>>::get_funding_owner(dispute.funding_id);
+
+        match (dispute.kind, verdict) {
+            // The undecided request goes on as it was before the dispute
+            (0, 4) => {
+                if <request::Module<T>>::restore_disputed_request(dispute.target).is_err() {
+                    // The request which can not go on fails
+                    <request::Module<T>>::reject_disputed_request(dispute.target);
+                }
+            },
+            (0, 1) => <request::Module<T>>::release_disputed_request(dispute.target)?,
+            (0, 2) => <request::Module<T>>::reject_disputed_request(dispute.target),
+            // Rejecting a funding or dissolving refunds the investors
+            (0, 3) | (1, 2) | (1, 3) => {
+                let owner = owner.clone().ok_or_else(|| Self::failed(DisputeError::OwnerNotExist))?;
+                <request::Module<T>>::dissolve_funding(dispute.funding_id, owner)?;
+                if dispute.kind == 0 {
+                    <request::Module<T>>::reject_disputed_request(dispute.target);
+                }
+            },
+            _ => {},
+        }
+
+        // The losing side's bond is slashed and the other one is returned, the challenger loses the undecided dispute
+        let (challenger_slashed, manager_slashed) = match verdict {
+            2 | 3 => (false, true),
+            _ => (true, false),
+        };
+        Self::settle_bond(&dispute_id, &dispute.challenger, dispute.bond, challenger_slashed);
+        if let Some(owner) = owner {
+            Self::settle_bond(&dispute_id, &owner, dispute.manager_bond, manager_slashed);
+        }
+
+        dispute.verdict = verdict;
+        <Disputes<T>>::insert(dispute_id.clone(), dispute.clone());
+        <DisputeOf<T>>::remove(dispute.target);
+        if dispute.kind == 1 {
+            <request::Module<T>>::set_funding_disputed(dispute.target, false);
+        }
+        Self::deposit_event(RawEvent::DisputeResolved(dispute_id, verdict));
+        Ok(())
+    }
+
+    fn settle_bond(dispute_id: &T::Hash, who: &T::AccountId, bond: T::Balance, slashed: bool) {
+        if bond == T::Balance::zero() {
+            return;
+        }
+        if slashed {
+            let _ = <balances::Module<T>>::slash_reserved(who, bond.clone());
+            Self::deposit_event(RawEvent::BondSlashed(dispute_id.clone(), who.clone(), bond));
+        } else {
+            let _ = <balances::Module<T>>::unreserve(who, bond);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    use support::{assert_ok, assert_err};
+    use runtime_io::with_externalities;
+    use primitives::H256;
+    use crate::funding_factory::tests::{FundingTest, Origin, build_ext, run_to_block, succeeded_funding};
+    use crate::request::tests::spending_request;
+
+    type Disputes = super::Module<FundingTest>;
+    type Requests = request::Module<FundingTest>;
+    type Balances = balances::Module<FundingTest>;
+
+    // A request of 100 approved by the investors #2 and #3 and disputed by #3, the arbitrators are #7, #8 and #9
+    fn disputed_request() -> (H256, H256) {
+        <MinDisputeStake<FundingTest>>::put(100);
+        assert_ok!(Disputes::set_arbitrators(Origin::ROOT, vec![7, 8, 9]));
+        let funding_id = succeeded_funding();
+        let request_id = spending_request(funding_id, 100);
+        assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+        assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+
+        assert_err!(Disputes::raise_dispute(Origin::signed(4), request_id, 0), "You are not the investor");
+        assert_ok!(Disputes::raise_dispute(Origin::signed(3), request_id, 0));
+        assert_eq!(Requests::get_request_status(request_id), 5);
+        let dispute_id = Disputes::dispute_of(request_id).unwrap();
+        (request_id, dispute_id)
+    }
+
+    #[test]
+    fn released_request_should_be_paid() {
+        with_externalities(&mut build_ext(), || {
+            let (request_id, dispute_id) = disputed_request();
+            assert_ok!(Disputes::defend_dispute(Origin::signed(1), dispute_id));
+            assert_err!(Disputes::arbitrate(Origin::signed(4), dispute_id, 1), "You are not the arbitrator");
+
+            assert_ok!(Disputes::arbitrate(Origin::signed(7), dispute_id, 1));
+            assert_ok!(Disputes::arbitrate(Origin::signed(8), dispute_id, 1));
+            assert_eq!(Requests::get_request_status(request_id), 1);
+            assert_eq!(Balances::free_balance(4), 100100);
+            // the challenger loses the bond and the manager gets it back
+            assert_eq!(Balances::free_balance(3), 98600);
+            assert_eq!(Balances::reserved_balance(3), 0);
+            assert_eq!(Balances::reserved_balance(1), 900);
+        })
+    }
+
+    #[test]
+    fn failed_release_should_not_count_the_vote() {
+        with_externalities(&mut build_ext(), || {
+            let (request_id, dispute_id) = disputed_request();
+            // the manager lost the money of the funding
+            let _ = Balances::unreserve(&1, 950);
+
+            assert_ok!(Disputes::arbitrate(Origin::signed(7), dispute_id, 1));
+            assert_err!(Disputes::arbitrate(Origin::signed(8), dispute_id, 1), "The reserved balance of the owner is not enough");
+            assert_eq!(Disputes::arbitration_vote_of((dispute_id, 8)), 0);
+            assert_eq!(Requests::get_request_status(request_id), 5);
+
+            assert_ok!(Disputes::arbitrate(Origin::signed(8), dispute_id, 2));
+            assert_ok!(Disputes::arbitrate(Origin::signed(9), dispute_id, 2));
+            assert_eq!(Requests::get_request_status(request_id), 2);
+            assert_eq!(Balances::reserved_balance(3), 0);
+            assert_eq!(Balances::free_balance(3), 99600);
+        })
+    }
+
+    #[test]
+    fn expired_dispute_should_keep_the_request() {
+        with_externalities(&mut build_ext(), || {
+            <DisputePeriod<FundingTest>>::put(10);
+            let (request_id, dispute_id) = disputed_request();
+
+            run_to_block(22);
+            // the approved request waits for the execution again and the challenger loses the bond
+            assert_eq!(Requests::get_request_status(request_id), 4);
+            assert_eq!(Requests::execute_at_of(request_id), 8661);
+            assert_eq!(Disputes::dispute_of(request_id), None);
+            assert_eq!(Balances::free_balance(3), 98600);
+            assert_err!(Disputes::arbitrate(Origin::signed(7), dispute_id, 2), "The dispute is over");
+        })
+    }
+
+    #[test]
+    fn disputed_funding_should_not_spend_money() {
+        with_externalities(&mut build_ext(), || {
+            <MinDisputeStake<FundingTest>>::put(100);
+            <DisputePeriod<FundingTest>>::put(10);
+            let funding_id = succeeded_funding();
+            assert_ok!(Disputes::raise_dispute(Origin::signed(3), funding_id, 1));
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 100)], None), "The funding is in dispute");
+
+            // the undecided dispute frees the money again
+            run_to_block(22);
+            assert_eq!(Disputes::dispute_of(funding_id), None);
+            spending_request(funding_id, 100);
+        })
+    }
+}
@@ -0,0 +1,65 @@
+//! The typed errors of the crowdfunding modules.
+
+/// Declare the error enum of a module with the stable code and the message of every error.
+///
+/// The error encodes as its code, so the `CallFailed` events carry the typed error into the
+/// metadata and the clients decode it with `from_code`. The calls return the message, and the
+/// dispatchable calls find the error back with `from_message` to record it.
+macro_rules! decl_error {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident {
+            $( $variant:ident = $code:tt => $message:expr, )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "std", derive(Debug))]
+        #[repr(u16)]
+        pub enum $name {
+            $( $variant = $code, )*
+        }
+
+        impl $name {
+            pub fn code(self) -> u16 {
+                self as u16
+            }
+
+            pub fn from_code(code: u16) -> Option<Self> {
+                match code {
+                    $( $code => Some($name::$variant), )*
+                    _ => None,
+                }
+            }
+
+            pub fn from_message(message: &str) -> Option<Self> {
+                $( if message == $message { return Some($name::$variant); } )*
+                None
+            }
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $( $name::$variant => $message, )*
+                }
+            }
+        }
+
+        impl From<$name> for &'static str {
+            fn from(error: $name) -> &'static str {
+                error.as_str()
+            }
+        }
+
+        impl parity_codec::Encode for $name {
+            fn encode_to<T: parity_codec::Output>(&self, dest: &mut T) {
+                parity_codec::Encode::encode_to(&self.code(), dest)
+            }
+        }
+
+        impl parity_codec::Decode for $name {
+            fn decode<I: parity_codec::Input>(input: &mut I) -> Option<Self> {
+                <u16 as parity_codec::Decode>::decode(input).and_then(Self::from_code)
+            }
+        }
+    }
+}
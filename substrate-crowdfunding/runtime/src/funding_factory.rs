@@ -1,9 +1,13 @@
 use support::{decl_storage, decl_module, StorageValue, StorageMap,
               dispatch::Result, ensure, decl_event, traits::{Currency, ReservableCurrency}};
-use system::ensure_signed;
-use runtime_primitives::traits::{As, Hash, Zero};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::{Permill, traits::{As, Hash, Zero}};
 use parity_codec::{Encode, Decode};
 use rstd::prelude::*;
+use runtime_io::blake2_256;
+
+/// The id that the keyless account of the treasury is derived from
+const TREASURY_ID: [u8; 8] = *b"cf/trsry";
 
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -18,16 +22,86 @@ pub struct Funding<Hash, AccountId, Balance, BlockNumber>{
     target_money: Balance,
     // the funding project deadline
     expiry: BlockNumber,
-    // status 0- Under Raising 1- Success 2- Failure
+    // status 0- Under Raising 1- Success 2- Failure 3- Dissolved
     status: u64,
 }
 
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct FundingDetails<Hash, AccountId, Balance, BlockNumber>{
+    pub funding_id: Hash,
+    pub manager: AccountId,
+    pub project_name: Vec<u8>,
+    pub target_money: Balance,
+    pub expiry: BlockNumber,
+    pub status: u64,
+    // the money that the investors have invested
+    pub raised: Balance,
+    // the number of the investors
+    pub investor_count: u64,
+    // the money left for the requests, zero unless the funding succeeded
+    pub remaining: Balance,
+}
+
+decl_error! {
+    /// The errors of the module, the codes are stable and must never be reused
+    pub enum FundingError {
+        FundingExists = 100 => "Funding already exists",
+        EnoughMoney = 101 => "You already have enough money",
+        ExpiryTooEarly = 102 => "The expiry has to be greater than the current block number",
+        ExpiryTooLate = 103 => "The expiry has be lower than the limit block number",
+        TooManyFundings = 104 => "Maximum number of fundings is reached for the target block, try another block",
+        AllowancePeriodZero = 105 => "The allowance period has to be greater than zero",
+        VelocityWindowZero = 106 => "The velocity window has to be greater than zero",
+        ApprovalCurveNotAscending = 107 => "The shares of the approval curve must ascend",
+        BudgetNotFull = 108 => "The shares of the budget lines must sum up to 100%",
+        NoOwner = 109 => "No owner of the funding",
+        InvestOwnFunding = 110 => "You can't invest for your own project",
+        FundingCountOverflow = 111 => "Overflow adding a new funding to total fundings",
+        OwnedFundingCountOverflow = 112 => "Overflow adding a new funding to account balance",
+        FundingNotExist = 113 => "The funding does not exist",
+        InsufficientBalance = 114 => "You don't have enough free balance for investing for the funding",
+        FundingExpired = 115 => "This funding is expired.",
+        InvestedFundingCountOverflow = 116 => "Overflow adding a new invested funding",
+        InvestorCountOverflow = 117 => "Overflow adding the total number of investors of a funding project",
+        BudgetLineNotExist = 118 => "The budget line does not exist",
+        BudgetLineShareNotEnough = 119 => "The share of the budget line is not enough",
+        InvestZero = 120 => "The invest amount has to be greater than zero",
+        PeriodLimitZero = 121 => "The period limit has to be greater than zero",
+        PerBlockCapZero = 122 => "The maximum number of fundings in a block has to be greater than zero",
+        TargetTooLow = 123 => "The target money is lower than the minimum",
+        NameTooLong = 124 => "The project name is longer than the maximum",
+        Paused = 125 => "Creating and investing fundings is paused",
+        FundingFrozen = 126 => "The funding is frozen",
+        ListingFeeUnpaid = 127 => "You don't have enough free balance for the listing fee",
+        SuccessFeeNotOwed = 128 => "The funding does not owe the success fee",
+    }
+}
+
+/// Handler for the investments into a funding
+pub trait OnInvest<AccountId, Hash, Balance> {
+    fn on_invest(funding_id: &Hash, investor: &AccountId, amount: Balance);
+}
+
+impl<AccountId, Hash, Balance> OnInvest<AccountId, Hash, Balance> for () {
+    fn on_invest(_: &Hash, _: &AccountId, _: Balance) {}
+}
+
+/// Handler for the end of a funding, returns the extra money that it paid to the owner of a succeeded funding
+pub trait OnFundingFinalized<AccountId, Hash, Balance> {
+    fn on_funding_finalized(funding_id: &Hash, owner: &AccountId, success: bool) -> Balance;
+}
+
+impl<AccountId, Hash, Balance: Default> OnFundingFinalized<AccountId, Hash, Balance> for () {
+    fn on_funding_finalized(_: &Hash, _: &AccountId, _: bool) -> Balance { Balance::default() }
+}
+
 pub trait Trait: balances::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    type OnInvest: OnInvest<Self::AccountId, Self::Hash, Self::Balance>;
+    type OnFundingFinalized: OnFundingFinalized<Self::AccountId, Self::Hash, Self::Balance>;
 }
 
-const MAX_FUNDINGS_PER_BLOCK: usize = 3;
-
 decl_event!(
     pub enum Event<T>
     where
@@ -39,6 +113,25 @@ decl_event!(
         CreateFunding(AccountId, Hash, Balance, Balance, BlockNumber),
         Invest(Hash, AccountId, Balance),
         FundingFinalized(Hash, Balance, BlockNumber, bool),
+        FundingPeriodLimitSet(BlockNumber),
+        MaxFundingsPerBlockSet(u32),
+        MinTargetMoneySet(Balance),
+        MaxNameLengthSet(u32),
+        /// Creating and investing fundings is paused or resumed for the reason
+        PausedSet(bool, Hash),
+        /// The funding is frozen or unfrozen for the reason
+        FundingFrozenSet(Hash, bool, Hash),
+        ListingFeeSet(Balance),
+        SuccessFeeSet(Permill),
+        /// The manager paid the listing fee of the funding to the treasury
+        ListingFeePaid(Hash, AccountId, Balance),
+        /// The succeeded funding paid the success fee to the treasury
+        SuccessFeePaid(Hash, Balance),
+        /// The succeeded funding could not pay the success fee, the manager keeps it reserved until it is paid
+        SuccessFeeFailed(Hash, Balance),
+        TreasurySpent(AccountId, Balance),
+        /// A call failed with the module error, which is encoded as its stable code
+        CallFailed(FundingError),
     }
 );
 
@@ -50,6 +143,12 @@ decl_storage! {
         FundingOwner get(owner_of): map T::Hash => Option<T::AccountId>;
         // Maximum time limit for the project
         FundingPeriodLimit get(funding_period_limit) config(): T::BlockNumber = T::BlockNumber::sa(777600);
+        // Maximum number of fundings ending in a block
+        MaxFundingsPerBlock get(max_fundings_per_block) config(): u32 = 3;
+        // Minimum money that a funding targets
+        MinTargetMoney get(min_target_money) config(): T::Balance;
+        // Maximum length of the project name in bytes
+        MaxNameLength get(max_name_length) config(): u32 = 128;
 
         // Projects ending in a block
         FundingsByBlockNumber get(funding_expire_at): map T::BlockNumber => Vec<T::Hash>;
@@ -78,12 +177,72 @@ decl_storage! {
         // The total amount of money the project has got
         FundingSupportedAmount get(total_amount_of_funding): map T::Hash => T::Balance;
 
+        // The money that the manager can spend without voting in every period
+        FundingAllowance get(allowance_of): map T::Hash => (T::Balance, T::BlockNumber);
+        // The minimum blocks between approved requests, the rolling window and the maximum share released in it
+        FundingVelocity get(velocity_of): map T::Hash => Option<(T::BlockNumber, T::BlockNumber, Permill)>;
+        // The ratio of the investors required to approve a request below each share of the remain money
+        FundingApprovalCurve get(approval_curve_of): map T::Hash => Vec<(Permill, Permill)>;
+        // The budget lines of the funding and their shares of the money
+        FundingBudget get(budget_of): map T::Hash => Vec<(Vec<u8>, Permill)>;
+
         // Get the status of a funding project: 1-success 2-fail
 //        FundingStatus get(funding_status): map T::Hash => u64;
 
+        // The fee that the manager pays to create a funding
+        ListingFee get(listing_fee) config(): T::Balance;
+        // The part of the raised money that a succeeded funding pays
+        SuccessFee get(success_fee) config(): Permill;
+        // The success fee that the funding paid or owes
+        SuccessFeeOf get(success_fee_of): map T::Hash => T::Balance;
+        // The success fee that the funding failed to pay, the manager keeps it reserved
+        SuccessFeeOwed get(success_fee_owed): map T::Hash => T::Balance;
+        // The money that the matching pools paid into the funding
+        MatchedOfFunding get(matched_of_funding): map T::Hash => T::Balance;
+
+        // Judge if creating and investing fundings is paused
+        Paused get(is_paused): bool;
+        // Judge if the funding is frozen, nobody invests it or spends its money
+        Frozen get(is_funding_frozen): map T::Hash => bool;
+
         // Record the number of funding
         Nonce: u64;
     }
+    add_extra_genesis {
+        // The fundings created at genesis: (manager, project name, target money, support money, expiry),
+        // a funding with a zero expiry is finalized at genesis after the investments
+        config(fundings): Vec<(T::AccountId, Vec<u8>, T::Balance, T::Balance, T::BlockNumber)>;
+        // The investments made at genesis: (index of the funding in the fundings, investor, money)
+        config(investments): Vec<(u64, T::AccountId, T::Balance)>;
+
+        build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+            runtime_io::with_storage(storage, || {
+                // the managers and the investors have to be endowed by the balances genesis
+                let mut closed = Vec::new();
+                for (manager, project_name, target_money, support_money, expiry) in &config.fundings {
+                    // a funding finalized at genesis ends in the first block until then
+                    let end = if expiry.is_zero() { T::BlockNumber::sa(1) } else { *expiry };
+                    let index = <Module<T>>::all_funding_count();
+                    <Module<T>>::create_funding(system::RawOrigin::Signed(manager.clone()).into(), project_name.clone(), *target_money, *support_money, end, T::Balance::zero(), T::BlockNumber::zero(), vec![], None, vec![])
+                        .unwrap_or_else(|e| panic!("Invalid genesis funding: {}", e));
+                    if expiry.is_zero() {
+                        let funding_id = <Module<T>>::funding_by_index(index);
+                        <FundingsByBlockNumber<T>>::mutate(end, |fundings| fundings.retain(|id| id != &funding_id));
+                        closed.push(funding_id);
+                    }
+                }
+                for (index, investor, amount) in &config.investments {
+                    let funding_id = <Module<T>>::funding_by_index(*index);
+                    <Module<T>>::invest(system::RawOrigin::Signed(investor.clone()).into(), funding_id, *amount)
+                        .unwrap_or_else(|e| panic!("Invalid genesis investment: {}", e));
+                }
+                for funding_id in &closed {
+                    <Fundings<T>>::mutate(funding_id, |funding| funding.expiry = T::BlockNumber::zero());
+                    <Module<T>>::finalize_funding(funding_id, T::BlockNumber::zero());
+                }
+            });
+        });
+    }
 }
 
 decl_module!{
@@ -92,122 +251,224 @@ decl_module!{
         fn deposit_event<T>() = default;
 
         /// Create a funding
-        fn create_funding(origin, project_name: Vec<u8>, target_money: T::Balance, support_money: T::Balance, expiry: T::BlockNumber) -> Result {
-            // get the sender
-            let sender = ensure_signed(origin)?;
-            // get the nonce to help generate unique id
-            let nonce = <Nonce<T>>::get();
-            // generate the unique id
-            let funding_id = (<system::Module<T>>::random_seed(), &sender, nonce)
-                .using_encoded(<T as system::Trait>::Hashing::hash);
-            // ensure that the funding id is unique
-            ensure!(!<FundingOwner<T>>::exists(&funding_id), "Funding already exists");
-            // ensure that the support_money less than target_money
-            ensure!(support_money <= target_money, "You already have enough money");
-            // create a new funding
-            let new_funding = Funding{
-                funding_id: funding_id.clone(),
-                manager: sender.clone(),
-                project_name: project_name,
-                target_money: target_money,
-                expiry: expiry,
-                status: 0,
-            };
-            // ensure that the expiry is valid
-            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
-            ensure!(expiry <= <system::Module<T>>::block_number() + Self::funding_period_limit(), "The expiry has be lower than the limit block number");
-
-            // ensure that the number of fundings in the block does not exceed maximum
-            let fundings = Self::funding_expire_at(expiry);
-            ensure!(fundings.len() < MAX_FUNDINGS_PER_BLOCK, "Maximum number of fundings is reached for the target block, try another block");
-
-            Self::mint(sender.clone(), funding_id.clone(), expiry.clone(), support_money.clone(), new_funding)?;
+        fn create_funding(origin, project_name: Vec<u8>, target_money: T::Balance, support_money: T::Balance, expiry: T::BlockNumber, allowance: T::Balance, allowance_period: T::BlockNumber, budget: Vec<(Vec<u8>, Permill)>, velocity: Option<(T::BlockNumber, T::BlockNumber, Permill)>, approval_curve: Vec<(Permill, Permill)>) -> Result {
+            Self::reported(|| {
+                // get the sender
+                let sender = ensure_signed(origin)?;
+                ensure!(!Self::is_paused(), Self::failed(FundingError::Paused));
+                // get the nonce to help generate unique id
+                let nonce = <Nonce<T>>::get();
+                // generate the unique id
+                let funding_id = (<system::Module<T>>::random_seed(), &sender, nonce)
+                    .using_encoded(<T as system::Trait>::Hashing::hash);
+                // ensure that the funding id is unique
+                ensure!(!<FundingOwner<T>>::exists(&funding_id), Self::failed(FundingError::FundingExists));
+                // ensure that the target and the name are within the limits
+                ensure!(target_money >= Self::min_target_money(), Self::failed(FundingError::TargetTooLow));
+                ensure!(project_name.len() <= Self::max_name_length() as usize, Self::failed(FundingError::NameTooLong));
+                // ensure that the support_money less than target_money
+                ensure!(support_money <= target_money, Self::failed(FundingError::EnoughMoney));
+                // create a new funding
+                let new_funding = Funding{
+                    funding_id: funding_id.clone(),
+                    manager: sender.clone(),
+                    project_name: project_name,
+                    target_money: target_money,
+                    expiry: expiry,
+                    status: 0,
+                };
+                // ensure that the expiry is valid
+                ensure!(expiry > <system::Module<T>>::block_number(), Self::failed(FundingError::ExpiryTooEarly));
+                ensure!(expiry <= <system::Module<T>>::block_number() + Self::funding_period_limit(), Self::failed(FundingError::ExpiryTooLate));
+
+                // ensure that the number of fundings in the block does not exceed maximum
+                let fundings = Self::funding_expire_at(expiry);
+                ensure!(fundings.len() < Self::max_fundings_per_block() as usize, Self::failed(FundingError::TooManyFundings));
+
+                // ensure that the allowance has a period
+                ensure!(allowance == T::Balance::zero() || allowance_period > T::BlockNumber::zero(), Self::failed(FundingError::AllowancePeriodZero));
+
+                // ensure that the velocity rule has a window
+                if let Some((_, window, _)) = velocity {
+                    ensure!(window > T::BlockNumber::zero(), Self::failed(FundingError::VelocityWindowZero));
+                }
 
-            // deposit the event
-            Self::deposit_event(RawEvent::CreateFunding(sender, funding_id, target_money, support_money, expiry));
-            Ok(())
+                // ensure that the shares of the approval curve ascend
+                for i in 1..approval_curve.len() {
+                    ensure!(approval_curve[i - 1].0 * 1_000_000u64 < approval_curve[i].0 * 1_000_000u64, Self::failed(FundingError::ApprovalCurveNotAscending));
+                }
+
+                // ensure that the shares of the budget lines sum up to 100%
+                if !budget.is_empty() {
+                    let total_share = budget.iter().fold(0u64, |total, (_, share)| total + *share * 1_000_000u64);
+                    ensure!(total_share == 1_000_000, Self::failed(FundingError::BudgetNotFull));
+                }
+
+                // ensure that the manager can pay the listing fee besides the support money
+                let listing_fee = Self::listing_fee();
+                ensure!(<balances::Module<T>>::free_balance(&sender) >= listing_fee + support_money, Self::failed(FundingError::ListingFeeUnpaid));
+
+                Self::mint(sender.clone(), funding_id.clone(), expiry.clone(), support_money.clone(), new_funding)?;
+
+                // pay the listing fee to the treasury, the funding is removed if it fails
+                if listing_fee > T::Balance::zero() {
+                    if <balances::Module<T> as Currency<_>>::transfer(&sender, &Self::treasury_account(), listing_fee).is_err() {
+                        Self::unmint(&sender, funding_id, expiry, support_money);
+                        return Err(Self::failed(FundingError::ListingFeeUnpaid));
+                    }
+                    Self::deposit_event(RawEvent::ListingFeePaid(funding_id, sender.clone(), listing_fee));
+                }
+
+                if allowance > T::Balance::zero() {
+                    <FundingAllowance<T>>::insert(funding_id.clone(), (allowance, allowance_period));
+                }
+                if !budget.is_empty() {
+                    <FundingBudget<T>>::insert(funding_id.clone(), budget);
+                }
+                if let Some(velocity) = velocity {
+                    <FundingVelocity<T>>::insert(funding_id.clone(), velocity);
+                }
+                if !approval_curve.is_empty() {
+                    <FundingApprovalCurve<T>>::insert(funding_id.clone(), approval_curve);
+                }
+
+                // deposit the event
+                Self::deposit_event(RawEvent::CreateFunding(sender, funding_id, target_money, support_money, expiry));
+                Ok(())
+            })
         }
 
         /// invest a project
         fn invest(origin, funding_id: T::Hash, invest_amount: T::Balance) -> Result {
-            let sender = ensure_signed(origin)?;
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                ensure!(!Self::is_paused(), Self::failed(FundingError::Paused));
+                ensure!(!Self::is_funding_frozen(funding_id), Self::failed(FundingError::FundingFrozen));
+
+                let owner = Self::owner_of(funding_id).ok_or_else(|| Self::failed(FundingError::NoOwner))?;
+                ensure!(owner != sender, Self::failed(FundingError::InvestOwnFunding));
+                ensure!(invest_amount > T::Balance::zero(), Self::failed(FundingError::InvestZero));
+
+                // The investor had not invested the project before
+                if !<InvestAmount<T>>::exists((funding_id.clone(), sender.clone())){
+                    Self::not_invest_before(sender.clone(), funding_id.clone(), invest_amount.clone())?;
+                }else{
+                    Self::invest_before(sender.clone(), funding_id.clone(), invest_amount.clone())?;
+                }
 
-            let owner = Self::owner_of(funding_id).ok_or("No owner of the funding")?;
-            ensure!(owner != sender, "You can't invest for your own project");
+                // deposit the event
+                Self::deposit_event(RawEvent::Invest(funding_id, sender.clone(), invest_amount));
+                T::OnInvest::on_invest(&funding_id, &sender, invest_amount);
 
-            // The investor had not invested the project before
-            if !<InvestAmount<T>>::exists((funding_id.clone(), sender.clone())){
-                Self::not_invest_before(sender.clone(), funding_id.clone(), invest_amount.clone())?;
-            }else{
-                Self::invest_before(sender.clone(), funding_id.clone(), invest_amount.clone())?;
-            }
+                Ok(())
+            })
+        }
 
-            // deposit the event
-            Self::deposit_event(RawEvent::Invest(funding_id, sender, invest_amount));
+        /// Change the limits of the fundings, the `None` ones are kept
+        fn set_parameters(origin, funding_period_limit: Option<T::BlockNumber>, max_fundings_per_block: Option<u32>, min_target_money: Option<T::Balance>, max_name_length: Option<u32>) -> Result {
+            Self::reported(|| {
+                ensure_root(origin)?;
+                if let Some(limit) = funding_period_limit {
+                    ensure!(limit > T::BlockNumber::zero(), Self::failed(FundingError::PeriodLimitZero));
+                }
+                ensure!(max_fundings_per_block != Some(0), Self::failed(FundingError::PerBlockCapZero));
 
-            Ok(())
+                if let Some(limit) = funding_period_limit {
+                    <FundingPeriodLimit<T>>::put(limit);
+                    Self::deposit_event(RawEvent::FundingPeriodLimitSet(limit));
+                }
+                if let Some(cap) = max_fundings_per_block {
+                    <MaxFundingsPerBlock<T>>::put(cap);
+                    Self::deposit_event(RawEvent::MaxFundingsPerBlockSet(cap));
+                }
+                if let Some(min) = min_target_money {
+                    <MinTargetMoney<T>>::put(min);
+                    Self::deposit_event(RawEvent::MinTargetMoneySet(min));
+                }
+                if let Some(length) = max_name_length {
+                    <MaxNameLength<T>>::put(length);
+                    Self::deposit_event(RawEvent::MaxNameLengthSet(length));
+                }
+                Ok(())
+            })
+        }
+
+        /// Change the fees, the `None` ones are kept
+        fn set_fees(origin, listing_fee: Option<T::Balance>, success_fee: Option<Permill>) -> Result {
+            Self::reported(|| {
+                ensure_root(origin)?;
+                if let Some(fee) = listing_fee {
+                    <ListingFee<T>>::put(fee);
+                    Self::deposit_event(RawEvent::ListingFeeSet(fee));
+                }
+                if let Some(fee) = success_fee {
+                    <SuccessFee<T>>::put(fee);
+                    Self::deposit_event(RawEvent::SuccessFeeSet(fee));
+                }
+                Ok(())
+            })
+        }
+
+        /// Spend the money of the treasury
+        fn spend_treasury(origin, to: T::AccountId, amount: T::Balance) -> Result {
+            Self::reported(|| {
+                ensure_root(origin)?;
+                <balances::Module<T> as Currency<_>>::transfer(&Self::treasury_account(), &to, amount)?;
+                Self::deposit_event(RawEvent::TreasurySpent(to, amount));
+                Ok(())
+            })
+        }
+
+        /// Pay the success fee that the funding owes to the treasury
+        fn pay_success_fee(origin, funding_id: T::Hash) -> Result {
+            Self::reported(|| {
+                let _ = ensure_signed(origin)?;
+                let fee = Self::success_fee_owed(funding_id);
+                ensure!(fee > T::Balance::zero(), Self::failed(FundingError::SuccessFeeNotOwed));
+                let owner = Self::owner_of(funding_id).ok_or_else(|| Self::failed(FundingError::NoOwner))?;
+
+                let _ = <balances::Module<T>>::unreserve(&owner, fee);
+                if let Err(e) = <balances::Module<T> as Currency<_>>::transfer(&owner, &Self::treasury_account(), fee) {
+                    // The fee stays reserved until it can be paid
+                    let _ = <balances::Module<T>>::reserve(&owner, fee);
+                    return Err(e);
+                }
+                <SuccessFeeOwed<T>>::remove(funding_id);
+                Self::deposit_event(RawEvent::SuccessFeePaid(funding_id, fee));
+                Ok(())
+            })
+        }
+
+        /// Pause or resume creating and investing fundings, the reason is the hash of the report
+        fn set_paused(origin, paused: bool, reason: T::Hash) -> Result {
+            Self::reported(|| {
+                ensure_root(origin)?;
+                <Paused<T>>::put(paused);
+                Self::deposit_event(RawEvent::PausedSet(paused, reason));
+                Ok(())
+            })
+        }
+
+        /// Freeze or unfreeze the funding, the reason is the hash of the report
+        fn freeze_funding(origin, funding_id: T::Hash, frozen: bool, reason: T::Hash) -> Result {
+            Self::reported(|| {
+                ensure_root(origin)?;
+                ensure!(<Fundings<T>>::exists(funding_id), Self::failed(FundingError::FundingNotExist));
+                if frozen {
+                    <Frozen<T>>::insert(funding_id, true);
+                } else {
+                    <Frozen<T>>::remove(funding_id);
+                }
+                Self::deposit_event(RawEvent::FundingFrozenSet(funding_id, frozen, reason));
+                Ok(())
+            })
         }
 
         fn on_finalize() {
             // get all the fundings of the block
             let block_number = <system::Module<T>>::block_number();
-            let funding_hashs = Self::funding_expire_at(block_number);
-
-
-            'outer: for funding_id in &funding_hashs{
-                // Get the funding
-                let mut funding = Self::funding_by_id(funding_id);
-                // Get the amount of money that the funding had raised
-                let amount_of_funding = Self::total_amount_of_funding(funding_id);
-                // If the money had raised more than the target_money, then the funding is success
-                if amount_of_funding >= funding.target_money{
-                    // Make the status success
-                    funding.status = 1;
-                    <Fundings<T>>::insert(funding_id.clone(), funding);
-                    // Get the owner of the funding
-                    let _owner = Self::owner_of(funding_id);
-                    match _owner {
-                        Some(owner) => {
-                            // Get all the investors
-                            let investors = Self::invest_accounts(funding_id);
-                            let mut no_error = true;
-                            // Iterate every investor, unreserve the money that he/she had invested and transfer it to owner
-                            'inner: for investor in &investors{
-                                let invest_balance = Self::invest_amount_of((*funding_id, investor.clone()));
-                                let _ = <balances::Module<T>>::unreserve(&investor, invest_balance.clone());
-                                // If the investor is owner, just unreserve the money
-                                if investor == &owner{ continue;}
-                                let _currency_transfer = <balances::Module<T> as Currency<_>>::transfer(&investor, &owner, invest_balance);
-                                match _currency_transfer {
-                                    Err(_e) => {
-                                        no_error = false;
-                                        break 'inner;
-                                    },
-                                    Ok(_v) => {}
-                                }
-                            }
-                            // If all the processes are right then reserve all money of the funding
-                            if no_error {
-                                let _ = <balances::Module<T>>::reserve(&owner, amount_of_funding);
-                                // deposit the event
-                                Self::deposit_event(RawEvent::FundingFinalized(*funding_id, amount_of_funding, block_number, true));
-                            }
-                        },
-                        None => continue,
-                    }
-                }else{ // refund all of the money
-                    // Make the status fail
-                    funding.status = 2;
-                    <Fundings<T>>::insert(funding_id.clone(), funding);
-                    let funding_accounts = Self::invest_accounts(funding_id);
-                    // refund all the money
-                    for account in funding_accounts {
-                        let invest_balance = Self::invest_amount_of((*funding_id, account.clone()));
-                        let _ = <balances::Module<T>>::unreserve(&account, invest_balance);
-                    }
-                    // deposit the event
-                    Self::deposit_event(RawEvent::FundingFinalized(*funding_id, amount_of_funding, block_number, false));
-                }
+            for funding_id in &Self::funding_expire_at(block_number) {
+                Self::finalize_funding(funding_id, block_number);
             }
         }
     }
@@ -215,13 +476,119 @@ decl_module!{
 
 impl<T: Trait> Module<T> {
 
+    // The message of the error, the dispatchable calls record the code for the clients
+    fn failed(error: FundingError) -> &'static str{
+        error.as_str()
+    }
+
+    // Run the body of a dispatchable call and record the error that it failed with
+    fn reported<F: FnOnce() -> Result>(call: F) -> Result{
+        call().map_err(|message| {
+            Self::report(message);
+            message
+        })
+    }
+
+    /// Record the error of the failed call if it is an error of the module, returns false otherwise
+    pub fn report(message: &'static str) -> bool{
+        match FundingError::from_message(message) {
+            Some(error) => {
+                Self::deposit_event(RawEvent::CallFailed(error));
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Make the funding succeed and pay the manager if it raised the target money, or fail and refund the investors
+    fn finalize_funding(funding_id: &T::Hash, block_number: T::BlockNumber){
+        // Get the funding
+        let mut funding = Self::funding_by_id(funding_id);
+        // Get the amount of money that the funding had raised
+        let amount_of_funding = Self::total_amount_of_funding(funding_id);
+        // If the money had raised more than the target_money, then the funding is success
+        if amount_of_funding >= funding.target_money{
+            // Make the status success
+            funding.status = 1;
+            let manager = funding.manager.clone();
+            <Fundings<T>>::insert(funding_id.clone(), funding);
+            // Get the owner of the funding
+            let _owner = Self::owner_of(funding_id);
+            match _owner {
+                Some(owner) => {
+                    // Get all the investors
+                    let investors = Self::invest_accounts(funding_id);
+                    let mut no_error = true;
+                    // Iterate every investor, unreserve the money that he/she had invested and transfer it to owner
+                    'inner: for investor in &investors{
+                        let invest_balance = Self::invest_amount_of((*funding_id, investor.clone()));
+                        let _ = <balances::Module<T>>::unreserve(&investor, invest_balance.clone());
+                        // If the investor is owner, just unreserve the money
+                        if investor == &owner{ continue;}
+                        let _currency_transfer = <balances::Module<T> as Currency<_>>::transfer(&investor, &owner, invest_balance);
+                        match _currency_transfer {
+                            Err(_e) => {
+                                no_error = false;
+                                break 'inner;
+                            },
+                            Ok(_v) => {}
+                        }
+                    }
+                    // If all the processes are right then pay the success fee and reserve the rest money of the funding
+                    if no_error {
+                        let fee = Self::success_fee() * amount_of_funding;
+                        let mut paid = T::Balance::zero();
+                        if fee > T::Balance::zero() {
+                            // The fee is never spent by the funding, an unpaid one stays reserved as owed
+                            <SuccessFeeOf<T>>::insert(funding_id, fee);
+                            if <balances::Module<T> as Currency<_>>::transfer(&owner, &Self::treasury_account(), fee).is_ok() {
+                                paid = fee;
+                                Self::deposit_event(RawEvent::SuccessFeePaid(*funding_id, fee));
+                            } else {
+                                <SuccessFeeOwed<T>>::insert(funding_id, fee);
+                                Self::deposit_event(RawEvent::SuccessFeeFailed(*funding_id, fee));
+                            }
+                        }
+                        let matched = T::OnFundingFinalized::on_funding_finalized(funding_id, &owner, true);
+                        if matched > T::Balance::zero() {
+                            <MatchedOfFunding<T>>::insert(funding_id, matched);
+                        }
+                        let _ = <balances::Module<T>>::reserve(&owner, amount_of_funding - paid + matched);
+                        // deposit the event
+                        Self::deposit_event(RawEvent::FundingFinalized(*funding_id, amount_of_funding, block_number, true));
+                    } else {
+                        // The matches are not paid, the pools get them back
+                        T::OnFundingFinalized::on_funding_finalized(funding_id, &owner, false);
+                    }
+                },
+                None => {
+                    T::OnFundingFinalized::on_funding_finalized(funding_id, &manager, false);
+                },
+            }
+        }else{ // refund all of the money
+            // Make the status fail
+            funding.status = 2;
+            let manager = funding.manager.clone();
+            <Fundings<T>>::insert(funding_id.clone(), funding);
+            T::OnFundingFinalized::on_funding_finalized(funding_id, &manager, false);
+            let funding_accounts = Self::invest_accounts(funding_id);
+            // refund all the money
+            for account in funding_accounts {
+                let invest_balance = Self::invest_amount_of((*funding_id, account.clone()));
+                let _ = <balances::Module<T>>::unreserve(&account, invest_balance);
+            }
+            // deposit the event
+            Self::deposit_event(RawEvent::FundingFinalized(*funding_id, amount_of_funding, block_number, false));
+        }
+    }
+
     fn mint(sender: T::AccountId, funding_id: T::Hash, expiry: T::BlockNumber, support_money: T::Balance, new_funding: Funding<T::Hash, T::AccountId, T::Balance, T::BlockNumber>) -> Result{
 
         let all_funding_count = Self::all_funding_count();
-        let new_all_funding_count = all_funding_count.checked_add(1).ok_or("Overflow adding a new funding to total fundings")?;
+        let new_all_funding_count = all_funding_count.checked_add(1).ok_or_else(|| Self::failed(FundingError::FundingCountOverflow))?;
 
         let owned_funding_count = Self::owned_funding_count(&sender);
-        let new_owned_funding_count = owned_funding_count.checked_add(1).ok_or("Overflow adding a new funding to account balance")?;
+        let new_owned_funding_count = owned_funding_count.checked_add(1).ok_or_else(|| Self::failed(FundingError::OwnedFundingCountOverflow))?;
 
         // change the global states
         <Fundings<T>>::insert(funding_id.clone(), new_funding.clone());
@@ -240,20 +607,10 @@ impl<T: Trait> Module<T> {
         <OwnedFundingIndex<T>>::insert((sender.clone(), funding_id.clone()), owned_funding_count);
 
         if support_money > T::Balance::sa(0) {
-            match Self::not_invest_before(sender.clone(), funding_id.clone(), support_money.clone()){
-                // If the invest function meets error then revert the storage
-                Err(_e) => {
-                    <Fundings<T>>::remove(funding_id.clone());
-                    <FundingOwner<T>>::remove(funding_id.clone());
-                    <FundingsByBlockNumber<T>>::mutate(expiry,|fundings| fundings.pop());
-                    <AllFundingArray<T>>::remove(&all_funding_count);
-                    <AllFundingCount<T>>::put(all_funding_count.clone());
-                    <AllFundingIndex<T>>::remove(funding_id.clone());
-                    <OwnedFundingArray<T>>::remove((sender.clone(), owned_funding_count.clone()));
-                    <OwnedFundingCount<T>>::remove(&sender);
-                    <OwnedFundingIndex<T>>::remove((sender.clone(), funding_id.clone()));
-                },
-                Ok(_v) => {}
+            // If the invest function meets error then revert the storage, it invested nothing
+            if let Err(e) = Self::not_invest_before(sender.clone(), funding_id.clone(), support_money.clone()) {
+                Self::unmint(&sender, funding_id, expiry, T::Balance::zero());
+                return Err(e);
             }
         }
 
@@ -263,17 +620,53 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
+    // Remove the funding minted last by the sender and return the support money that it invested
+    fn unmint(sender: &T::AccountId, funding_id: T::Hash, expiry: T::BlockNumber, support_money: T::Balance){
+        let all_funding_count = Self::all_funding_count() - 1;
+        let owned_funding_count = Self::owned_funding_count(sender) - 1;
+
+        <Fundings<T>>::remove(funding_id);
+        <FundingOwner<T>>::remove(funding_id);
+        <FundingsByBlockNumber<T>>::mutate(expiry, |fundings| fundings.retain(|id| id != &funding_id));
+        <AllFundingArray<T>>::remove(&all_funding_count);
+        <AllFundingCount<T>>::put(all_funding_count);
+        <AllFundingIndex<T>>::remove(funding_id);
+        <OwnedFundingArray<T>>::remove((sender.clone(), owned_funding_count));
+        <OwnedFundingCount<T>>::insert(sender, owned_funding_count);
+        <OwnedFundingIndex<T>>::remove((sender.clone(), funding_id));
+
+        if support_money > T::Balance::zero() {
+            let _ = <balances::Module<T>>::unreserve(sender, support_money);
+            let invested_funding_count = Self::invested_funding_count(sender) - 1;
+            <InvestAmount<T>>::remove((funding_id, sender.clone()));
+            <InvestAccounts<T>>::remove(funding_id);
+            <InvestAccountsCount<T>>::remove(funding_id);
+            <InvestedFundingsArray<T>>::remove((sender.clone(), invested_funding_count));
+            <InvestedFundingsCount<T>>::insert(sender, invested_funding_count);
+            <InvestedFundingsIndex<T>>::remove((sender.clone(), funding_id));
+            <FundingSupportedAmount<T>>::remove(funding_id);
+        }
+    }
+
+    /// The keyless account of the treasury collecting the fees, only `spend_treasury` spends it
+    pub fn treasury_account() -> T::AccountId {
+        let hash = (b"modl", TREASURY_ID).using_encoded(blake2_256);
+        // The account id of the runtime is a 32 byte public key and the one of the tests a u64,
+        // both decode from the first bytes of the 32 byte hash
+        T::AccountId::decode(&mut &hash[..]).expect("The account id is at most 32 bytes long; qed")
+    }
+
     //The investor had invested the project before
     fn invest_before(sender: T::AccountId, funding_id: T::Hash, invest_amount: T::Balance) -> Result{
         // ensure the funding exists
-        ensure!(<Fundings<T>>::exists(funding_id), "The funding does not exist");
+        ensure!(<Fundings<T>>::exists(funding_id), Self::failed(FundingError::FundingNotExist));
         // ensure the investor has enough money
-        ensure!(<balances::Module<T>>::free_balance(sender.clone()) >= invest_amount, "You don't have enough free balance for investing for the funding");
+        ensure!(<balances::Module<T>>::free_balance(sender.clone()) >= invest_amount, Self::failed(FundingError::InsufficientBalance));
 
         // get the funding
         let funding = Self::funding_by_id(&funding_id);
         // ensure that the project is valid to invest
-        ensure!(<system::Module<T>>::block_number() < funding.expiry, "This funding is expired.");
+        ensure!(<system::Module<T>>::block_number() < funding.expiry, Self::failed(FundingError::FundingExpired));
 
         // reserve the amount of money
         <balances::Module<T>>::reserve(&sender, invest_amount)?;
@@ -297,21 +690,21 @@ impl<T: Trait> Module<T> {
     // The investor doesn't invest the project before
     fn not_invest_before(sender: T::AccountId, funding_id: T::Hash, invest_amount: T::Balance) -> Result{
         // ensure the funding exists
-        ensure!(<Fundings<T>>::exists(funding_id), "The funding does not exist");
+        ensure!(<Fundings<T>>::exists(funding_id), Self::failed(FundingError::FundingNotExist));
         // ensure that the investor has enough money
-        ensure!(<balances::Module<T>>::free_balance(sender.clone()) >= invest_amount, "You don't have enough free balance for investing for the funding");
+        ensure!(<balances::Module<T>>::free_balance(sender.clone()) >= invest_amount, Self::failed(FundingError::InsufficientBalance));
 
         // get the number of projects that the investor had invested and add it
         let invested_funding_count = Self::invested_funding_count(&sender);
-        let new_invested_funding_count = invested_funding_count.checked_add(1).ok_or("Overflow adding a new invested funding")?;
+        let new_invested_funding_count = invested_funding_count.checked_add(1).ok_or_else(|| Self::failed(FundingError::InvestedFundingCountOverflow))?;
 
         let investor_count = <InvestAccountsCount<T>>::get(&funding_id);
-        let new_investor_count = investor_count.checked_add(1).ok_or("Overflow adding the total number of investors of a funding project")?;
+        let new_investor_count = investor_count.checked_add(1).ok_or_else(|| Self::failed(FundingError::InvestorCountOverflow))?;
 
         // get the funding
         let funding = Self::funding_by_id(&funding_id);
         // ensure that the project is valid to invest
-        ensure!(<system::Module<T>>::block_number() < funding.expiry, "This funding is expired.");
+        ensure!(<system::Module<T>>::block_number() < funding.expiry, Self::failed(FundingError::FundingExpired));
 
         // reserve the amount of money
         <balances::Module<T>>::reserve(&sender, invest_amount)?;
@@ -350,8 +743,9 @@ impl<T: Trait> Module<T> {
         <FundingOwner<T>>::get(funding_id)
     }
 
+    // The money that the funding can spend, the raised and matched money without the success fee
     pub fn get_funding_total_balance(funding_id: T::Hash) -> T::Balance{
-        <FundingSupportedAmount<T>>::get(funding_id)
+        <FundingSupportedAmount<T>>::get(funding_id) + Self::matched_of_funding(funding_id) - Self::success_fee_of(funding_id)
     }
 
     pub fn is_investor(funding_id: T::Hash, from: T::AccountId) -> bool{
@@ -361,20 +755,149 @@ impl<T: Trait> Module<T> {
     pub fn get_invested_number(funding_id: T::Hash) -> u64{
         <InvestAccountsCount<T>>::get(funding_id)
     }
+
+    pub fn get_invest_accounts(funding_id: T::Hash) -> Vec<T::AccountId>{
+        <InvestAccounts<T>>::get(funding_id)
+    }
+
+    pub fn get_invest_amount(funding_id: T::Hash, from: T::AccountId) -> T::Balance{
+        <InvestAmount<T>>::get((funding_id, from))
+    }
+
+    pub fn investments_of(funding_id: T::Hash) -> Vec<(T::AccountId, T::Balance)>{
+        Self::invest_accounts(funding_id).into_iter()
+            .map(|investor| {
+                let amount = Self::get_invest_amount(funding_id, investor.clone());
+                (investor, amount)
+            })
+            .collect()
+    }
+
+    pub fn get_funding_allowance(funding_id: T::Hash) -> (T::Balance, T::BlockNumber){
+        <FundingAllowance<T>>::get(funding_id)
+    }
+
+    pub fn get_velocity_rule(funding_id: T::Hash) -> Option<(T::BlockNumber, T::BlockNumber, Permill)>{
+        <FundingVelocity<T>>::get(funding_id)
+    }
+
+    // The approval curve of the funding, a majority below 10% of the remain money, two thirds below 50% and 90% above by default
+    pub fn get_approval_curve(funding_id: T::Hash) -> Vec<(Permill, Permill)>{
+        let curve = <FundingApprovalCurve<T>>::get(funding_id);
+        if !curve.is_empty() {
+            return curve;
+        }
+        vec![
+            (Permill::from_percent(10), Permill::from_percent(50)),
+            (Permill::from_percent(50), Permill::from_millionths(666_666)),
+            (Permill::from_percent(100), Permill::from_percent(90)),
+        ]
+    }
+
+    // The cap of the budget line, None if the funding has no budget lines
+    pub fn get_budget_line_cap(funding_id: T::Hash, budget_line: u32) -> rstd::result::Result<Option<T::Balance>, &'static str>{
+        let budget = <FundingBudget<T>>::get(funding_id);
+        if budget.is_empty() {
+            return Ok(None);
+        }
+        let (_, share) = budget.get(budget_line as usize).ok_or_else(|| Self::failed(FundingError::BudgetLineNotExist))?;
+        Ok(Some(*share * Self::get_funding_total_balance(funding_id)))
+    }
+
+    // The budget lines exist and the line that gives the share away has it
+    pub fn check_reallocate_budget(funding_id: T::Hash, from: u32, to: u32, share: Permill) -> Result{
+        let budget = <FundingBudget<T>>::get(funding_id);
+        ensure!((from as usize) < budget.len() && (to as usize) < budget.len(), Self::failed(FundingError::BudgetLineNotExist));
+        ensure!(budget[from as usize].1 * 1_000_000u64 >= share * 1_000_000u64, Self::failed(FundingError::BudgetLineShareNotEnough));
+        Ok(())
+    }
+
+    // Move a share of the money from one budget line to another
+    pub fn reallocate_budget(funding_id: T::Hash, from: u32, to: u32, share: Permill) -> Result{
+        Self::check_reallocate_budget(funding_id, from, to, share)?;
+        let mut budget = <FundingBudget<T>>::get(funding_id);
+        let share = share * 1_000_000u64;
+        let from_share = budget[from as usize].1 * 1_000_000u64;
+        let to_share = budget[to as usize].1 * 1_000_000u64;
+        budget[from as usize].1 = Permill::from_millionths((from_share - share) as u32);
+        budget[to as usize].1 = Permill::from_millionths((to_share + share) as u32);
+        <FundingBudget<T>>::insert(funding_id, budget);
+        Ok(())
+    }
+
+    // Assemble the funding with its investments, the remaining money is filled by the request module
+    pub fn funding_details(funding_id: T::Hash) -> Option<FundingDetails<T::Hash, T::AccountId, T::Balance, T::BlockNumber>>{
+        if !<Fundings<T>>::exists(funding_id) {
+            return None;
+        }
+        let funding = Self::funding_by_id(funding_id);
+        let raised = Self::total_amount_of_funding(funding_id);
+        Some(FundingDetails{
+            funding_id,
+            manager: funding.manager,
+            project_name: funding.project_name,
+            target_money: funding.target_money,
+            expiry: funding.expiry,
+            status: funding.status,
+            raised,
+            investor_count: Self::invest_accounts_count(funding_id),
+            remaining: if funding.status == 1 { raised } else { T::Balance::zero() },
+        })
+    }
+
+    // A page of all the fundings, optionally only the ones with the status
+    pub fn list_fundings(offset: u64, limit: u64, status_filter: Option<u64>) -> Vec<T::Hash>{
+        (0..Self::all_funding_count())
+            .map(|index| Self::funding_by_index(index))
+            .filter(|funding_id| status_filter.map_or(true, |status| Self::funding_by_id(funding_id).status == status))
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn fundings_of_owner(owner: T::AccountId) -> Vec<T::Hash>{
+        (0..Self::owned_funding_count(&owner))
+            .map(|index| Self::funding_of_owner_by_index((owner.clone(), index)))
+            .collect()
+    }
+
+    pub fn fundings_of_investor(investor: T::AccountId) -> Vec<T::Hash>{
+        (0..Self::invested_funding_count(&investor))
+            .map(|index| Self::invested_funding_by_index((investor.clone(), index)))
+            .collect()
+    }
+
+    // Refund the investors of a raising funding and dissolve it before the expiry, returns the refunds
+    pub fn cancel_raising(funding_id: T::Hash) -> Vec<(T::AccountId, T::Balance)>{
+        let funding = Self::funding_by_id(&funding_id);
+        <FundingsByBlockNumber<T>>::mutate(funding.expiry, |fundings| fundings.retain(|id| id != &funding_id));
+        T::OnFundingFinalized::on_funding_finalized(&funding_id, &funding.manager, false);
+        let refunds = Self::investments_of(funding_id);
+        for (investor, amount) in &refunds {
+            let _ = <balances::Module<T>>::unreserve(investor, *amount);
+        }
+        Self::set_funding_dissolved(funding_id);
+        refunds
+    }
+
+    pub fn set_funding_dissolved(funding_id: T::Hash){
+        <Fundings<T>>::mutate(funding_id, |funding| funding.status = 3);
+    }
 }
 
 #[cfg(test)]
-mod tests{
+pub mod tests{
     use super::*;
 
-    use support::{impl_outer_origin, assert_ok, assert_noop};
+    use support::{impl_outer_origin, assert_ok, assert_noop, assert_err};
     use runtime_io::{with_externalities, TestExternalities};
     use primitives::{H256, Blake2Hasher};
     use runtime_primitives::{
         BuildStorage,
-        traits::{BlakeTwo256, IdentityLookup},
+        traits::{BlakeTwo256, IdentityLookup, OnFinalize},
         testing::{Digest, DigestItem, Header}
     };
+    use crate::{request, dispute, matching};
 
     impl_outer_origin! {
         pub enum Origin for FundingTest {}
@@ -407,23 +930,80 @@ mod tests{
         type DustRemoval = ();
     }
 
+    impl timestamp::Trait for FundingTest {
+        type Moment = u64;
+        type OnTimestampSet = ();
+    }
+
     impl super::Trait for FundingTest {
         type Event = ();
+        type OnInvest = matching::Module<FundingTest>;
+        type OnFundingFinalized = matching::Module<FundingTest>;
+    }
+
+    impl request::Trait for FundingTest {
+        type Event = ();
+    }
+
+    impl dispute::Trait for FundingTest {
+        type Event = ();
+    }
+
+    impl matching::Trait for FundingTest {
+        type Event = ();
     }
 
     type Fundings = super::Module<FundingTest>;
+    type System = system::Module<FundingTest>;
+    type Balances = balances::Module<FundingTest>;
 
-    fn build_ext() -> TestExternalities<Blake2Hasher> {
+    // The accounts #1 to #9 start with 100000 each
+    pub fn build_ext() -> TestExternalities<Blake2Hasher> {
         let mut t = system::GenesisConfig::<FundingTest>::default().build_storage().unwrap().0;
-        t.extend(balances::GenesisConfig::<FundingTest>::default().build_storage().unwrap().0);
+        t.extend(balances::GenesisConfig::<FundingTest>{
+            balances: (1..10).map(|account| (account, 100000)).collect(),
+            transaction_base_fee: 0,
+            transaction_byte_fee: 0,
+            existential_deposit: 0,
+            transfer_fee: 0,
+            creation_fee: 0,
+            vesting: vec![],
+        }.build_storage().unwrap().0);
         t.into()
     }
 
+    // Finalize the blocks of all the modules until the block number is n
+    pub fn run_to_block(n: u64) {
+        while System::block_number() < n {
+            let block_number = System::block_number();
+            Fundings::on_finalize(block_number);
+            <request::Module<FundingTest>>::on_finalize(block_number);
+            <dispute::Module<FundingTest>>::on_finalize(block_number);
+            System::set_block_number(block_number + 1);
+        }
+    }
+
+    // The funding created last
+    pub fn last_funding() -> H256 {
+        Fundings::funding_by_index(Fundings::all_funding_count() - 1)
+    }
+
+    // A funding of the manager #1 targeting 1000, invested 600 by #2 and 400 by #3 and succeeded at the block 10
+    pub fn succeeded_funding() -> H256 {
+        assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+        let funding_id = last_funding();
+        assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 600));
+        assert_ok!(Fundings::invest(Origin::signed(3), funding_id, 400));
+        run_to_block(11);
+        assert_eq!(Fundings::is_funding_success(funding_id), 1);
+        funding_id
+    }
+
     #[test]
     fn create_funding_should_work() {
         with_externalities(&mut build_ext(), || {
             // create a funding with account #6.
-            assert_ok!(Fundings::create_funding(Origin::signed(6), vec![12,56], 20000, 0, 1000));
+            assert_ok!(Fundings::create_funding(Origin::signed(6), vec![12,56], 20000, 0, 1000, 0, 0, vec![], None, vec![]));
 
             // check that there are now 3 fundings in storage
             assert_eq!(Fundings::all_funding_count(), 1);
@@ -442,4 +1022,171 @@ mod tests{
             assert_eq!(hash, other_hash);
         })
     }
+
+    #[test]
+    fn invest_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+            let funding_id = last_funding();
+
+            assert_err!(Fundings::invest(Origin::signed(1), funding_id, 100), "You can't invest for your own project");
+            assert_err!(Fundings::invest(Origin::signed(2), funding_id, 0), "The invest amount has to be greater than zero");
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 100));
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 50));
+            assert_eq!(Fundings::total_amount_of_funding(funding_id), 150);
+            assert_eq!(Fundings::get_invested_number(funding_id), 1);
+            assert_eq!(Fundings::investments_of(funding_id), vec![(2, 150)]);
+        })
+    }
+
+    #[test]
+    fn genesis_should_finalize_the_closed_fundings() {
+        let mut t = system::GenesisConfig::<FundingTest>::default().build_storage().unwrap().0;
+        t.extend(balances::GenesisConfig::<FundingTest>{
+            balances: (1..10).map(|account| (account, 100000)).collect(),
+            transaction_base_fee: 0,
+            transaction_byte_fee: 0,
+            existential_deposit: 0,
+            transfer_fee: 0,
+            creation_fee: 0,
+            vesting: vec![],
+        }.build_storage().unwrap().0);
+        // the fundings are built over the endowed balances
+        let mut children = Default::default();
+        GenesisConfig::<FundingTest>{
+            funding_period_limit: 777600,
+            max_fundings_per_block: 3,
+            min_target_money: 0,
+            max_name_length: 128,
+            listing_fee: 0,
+            success_fee: Permill::from_percent(0),
+            fundings: vec![(1, b"garden".to_vec(), 1000, 0, 0), (4, b"editor".to_vec(), 5000, 0, 100)],
+            investments: vec![(0, 2, 600), (0, 3, 400), (1, 5, 200)],
+        }.assimilate_storage(&mut t, &mut children).unwrap();
+
+        with_externalities(&mut TestExternalities::<Blake2Hasher>::from(t), || {
+            let closed = Fundings::funding_by_index(0);
+            assert_eq!(Fundings::is_funding_success(closed), 1);
+            assert!(Fundings::funding_expire_at(1).is_empty());
+            assert_eq!(Balances::reserved_balance(1), 1000);
+            assert_eq!(Balances::free_balance(2), 99400);
+
+            let raising = Fundings::funding_by_index(1);
+            assert_eq!(Fundings::is_funding_success(raising), 0);
+            assert_eq!(Fundings::total_amount_of_funding(raising), 200);
+            assert_eq!(Fundings::funding_expire_at(100), vec![raising]);
+        });
+    }
+
+    #[test]
+    fn set_parameters_should_work() {
+        with_externalities(&mut build_ext(), || {
+            // only root can change the parameters
+            assert_noop!(Fundings::set_parameters(Origin::signed(6), Some(100), None, None, None), "bad origin: expected to be a root origin");
+            assert_err!(Fundings::set_parameters(Origin::ROOT, None, Some(0), None, None), "The maximum number of fundings in a block has to be greater than zero");
+
+            assert_ok!(Fundings::set_parameters(Origin::ROOT, Some(100), None, Some(1000), Some(1)));
+            assert_eq!(Fundings::funding_period_limit(), 100);
+            assert_eq!(Fundings::max_fundings_per_block(), 3);
+
+            // the new limits apply to the new fundings
+            assert_err!(Fundings::create_funding(Origin::signed(6), vec![12], 999, 0, 50, 0, 0, vec![], None, vec![]), "The target money is lower than the minimum");
+            assert_err!(Fundings::create_funding(Origin::signed(6), vec![12,56], 20000, 0, 50, 0, 0, vec![], None, vec![]), "The project name is longer than the maximum");
+            assert_err!(Fundings::create_funding(Origin::signed(6), vec![12], 20000, 0, 1000, 0, 0, vec![], None, vec![]), "The expiry has be lower than the limit block number");
+            assert_ok!(Fundings::create_funding(Origin::signed(6), vec![12], 20000, 0, 50, 0, 0, vec![], None, vec![]));
+        })
+    }
+
+    #[test]
+    fn pause_and_freeze_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+            let funding_id = last_funding();
+            let reason = H256::repeat_byte(1);
+
+            // only root can pause
+            assert_noop!(Fundings::set_paused(Origin::signed(1), true, reason), "bad origin: expected to be a root origin");
+            assert_ok!(Fundings::set_paused(Origin::ROOT, true, reason));
+            assert_err!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]), "Creating and investing fundings is paused");
+            assert_err!(Fundings::invest(Origin::signed(2), funding_id, 100), "Creating and investing fundings is paused");
+            assert_ok!(Fundings::set_paused(Origin::ROOT, false, reason));
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 100));
+
+            // only root can freeze an existing funding
+            assert_noop!(Fundings::freeze_funding(Origin::signed(1), funding_id, true, reason), "bad origin: expected to be a root origin");
+            assert_err!(Fundings::freeze_funding(Origin::ROOT, H256::repeat_byte(2), true, reason), "The funding does not exist");
+            assert_ok!(Fundings::freeze_funding(Origin::ROOT, funding_id, true, reason));
+            assert!(Fundings::is_funding_frozen(funding_id));
+            assert_err!(Fundings::invest(Origin::signed(2), funding_id, 100), "The funding is frozen");
+            assert_ok!(Fundings::freeze_funding(Origin::ROOT, funding_id, false, reason));
+            assert!(!Fundings::is_funding_frozen(funding_id));
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 100));
+            assert_eq!(Fundings::total_amount_of_funding(funding_id), 200);
+        })
+    }
+
+    #[test]
+    fn listing_fee_should_be_paid_last() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::set_fees(Origin::ROOT, Some(100), None));
+            let treasury = Fundings::treasury_account();
+
+            // the fee and the support money exceed the free balance, nothing is paid or created
+            assert_err!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 100000, 99950, 10, 0, 0, vec![], None, vec![]), "You don't have enough free balance for the listing fee");
+            assert_eq!(Fundings::all_funding_count(), 0);
+            assert_eq!(Balances::free_balance(1), 100000);
+            assert_eq!(Balances::free_balance(&treasury), 0);
+
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 500, 10, 0, 0, vec![], None, vec![]));
+            assert_eq!(Fundings::all_funding_count(), 1);
+            assert_eq!(Balances::free_balance(1), 99400);
+            assert_eq!(Balances::reserved_balance(1), 500);
+            assert_eq!(Balances::free_balance(&treasury), 100);
+        })
+    }
+
+    #[test]
+    fn success_fee_should_go_to_the_treasury() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::set_fees(Origin::ROOT, None, Some(Permill::from_percent(2))));
+            let treasury = Fundings::treasury_account();
+
+            let funding_id = succeeded_funding();
+            assert_eq!(Fundings::success_fee_of(funding_id), 20);
+            assert_eq!(Fundings::get_funding_total_balance(funding_id), 980);
+            assert_eq!(Balances::reserved_balance(1), 980);
+            assert_eq!(Balances::free_balance(&treasury), 20);
+
+            // only root spends the treasury
+            assert_noop!(Fundings::spend_treasury(Origin::signed(1), 5, 10), "bad origin: expected to be a root origin");
+            assert_ok!(Fundings::spend_treasury(Origin::ROOT, 5, 10));
+            assert_eq!(Balances::free_balance(&treasury), 10);
+            assert_eq!(Balances::free_balance(5), 100010);
+        })
+    }
+
+    #[test]
+    fn unpaid_success_fee_should_stay_owed() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::set_fees(Origin::ROOT, None, Some(Permill::from_percent(2))));
+            let treasury = Fundings::treasury_account();
+            // the treasury can't receive any more money
+            <Balances as Currency<_>>::make_free_balance_be(&treasury, u64::max_value());
+
+            let funding_id = succeeded_funding();
+            assert_eq!(Fundings::success_fee_of(funding_id), 20);
+            assert_eq!(Fundings::success_fee_owed(funding_id), 20);
+            assert_eq!(Fundings::get_funding_total_balance(funding_id), 980);
+            assert_eq!(Balances::reserved_balance(1), 1000);
+            assert_err!(Fundings::pay_success_fee(Origin::signed(2), funding_id), "destination balance too high to receive value");
+            assert_eq!(Balances::reserved_balance(1), 1000);
+
+            <Balances as Currency<_>>::make_free_balance_be(&treasury, 0);
+            assert_ok!(Fundings::pay_success_fee(Origin::signed(2), funding_id));
+            assert_eq!(Fundings::success_fee_owed(funding_id), 0);
+            assert_eq!(Balances::reserved_balance(1), 980);
+            assert_eq!(Balances::free_balance(&treasury), 20);
+            assert_err!(Fundings::pay_success_fee(Origin::signed(2), funding_id), "The funding does not owe the success fee");
+        })
+    }
 }
@@ -18,7 +18,7 @@ use runtime_primitives::{
 };
 use client::{
 	block_builder::api::{CheckInherentsResult, InherentData, self as block_builder_api},
-	runtime_api, impl_runtime_apis
+	runtime_api, impl_runtime_apis, decl_runtime_apis
 };
 use version::RuntimeVersion;
 #[cfg(feature = "std")]
@@ -52,13 +52,23 @@ pub type Hash = primitives::H256;
 /// Index of a block number in the chain.
 pub type BlockNumber = u64;
 
+/// The type for recording an account's balance.
+pub type Balance = u128;
+
 /// Index of an account's extrinsic in the chain.
 pub type Nonce = u64;
 
+/// The macro declaring the errors of the modules
+#[macro_use]
+mod errors;
 /// Import funding module
-mod funding_factory;
+pub mod funding_factory;
 /// Import request module
-mod request;
+pub mod request;
+/// Import dispute module
+pub mod dispute;
+/// Import matching module
+pub mod matching;
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -97,7 +107,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("substrate-crowdfunding"),
 	impl_name: create_runtime_str!("substrate-crowdfunding"),
 	authoring_version: 3,
-	spec_version: 3,
+	spec_version: 4,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 };
@@ -170,7 +180,7 @@ impl timestamp::Trait for Runtime {
 
 impl balances::Trait for Runtime {
 	/// The type for recording an account's balance.
-	type Balance = u128;
+	type Balance = Balance;
 	/// What to do if an account's free balance gets zeroed.
 	type OnFreeBalanceZero = ();
 	/// What to do if a new account is created.
@@ -192,12 +202,22 @@ impl sudo::Trait for Runtime {
 // import the trait of our module
 impl funding_factory::Trait for Runtime {
 	type Event = Event;
+	type OnInvest = Matching;
+	type OnFundingFinalized = Matching;
 }
 
 impl request::Trait for Runtime{
 	type Event = Event;
 }
 
+impl dispute::Trait for Runtime{
+	type Event = Event;
+}
+
+impl matching::Trait for Runtime{
+	type Event = Event;
+}
+
 construct_runtime!(
 	pub enum Runtime with Log(InternalLog: DigestItem<Hash, AuthorityId, AuthoritySignature>) where
 		Block = Block,
@@ -212,9 +232,13 @@ construct_runtime!(
 		Balances: balances,
 		Sudo: sudo,
 		// import funding part
-		FundingFactory: funding_factory::{Module, Call, Storage, Event<T>},
+		FundingFactory: funding_factory::{Module, Call, Storage, Config<T>, Event<T>},
 		// import request part
-		Request: request::{Module, Call, Storage, Event<T>},
+		Request: request::{Module, Call, Storage, Config<T>, Event<T>},
+		// import dispute part
+		Dispute: dispute::{Module, Call, Storage, Config<T>, Event<T>},
+		// import matching part
+		Matching: matching::{Module, Call, Storage, Event<T>},
 	}
 );
 
@@ -232,9 +256,39 @@ pub type BlockId = generic::BlockId<Block>;
 pub type UncheckedExtrinsic = generic::UncheckedMortalCompactExtrinsic<Address, Nonce, Call, AccountSignature>;
 /// Extrinsic type that has already been checked.
 pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Nonce, Call>;
+/// The events deposited in a block, as kept in the storage of the system module.
+pub type EventRecords = Vec<system::EventRecord<Event>>;
+pub use system::{Phase, Event as SystemEvent};
 /// Executive: handles dispatch to the various modules.
 pub type Executive = executive::Executive<Runtime, Block, Context, Balances, AllModules>;
 
+/// A funding with its raised money, investors and remaining balance.
+pub type FundingDetails = funding_factory::FundingDetails<Hash, AccountId, Balance, BlockNumber>;
+/// A request with its votes and beneficiaries.
+pub type RequestDetails = request::RequestDetails<Hash, AccountId, Balance, BlockNumber>;
+
+decl_runtime_apis! {
+	/// The queries of the crowdfunding modules.
+	pub trait CrowdfundingApi {
+		/// The funding, `None` if it does not exist.
+		fn funding_details(funding_id: Hash) -> Option<FundingDetails>;
+		/// A page of all the fundings, optionally only the ones with the status.
+		fn list_fundings(offset: u64, limit: u64, status_filter: Option<u64>) -> Vec<FundingDetails>;
+		/// The fundings that the account raises.
+		fn fundings_of_owner(owner: AccountId) -> Vec<FundingDetails>;
+		/// The fundings that the account has invested.
+		fn fundings_of_investor(investor: AccountId) -> Vec<FundingDetails>;
+		/// The money that the account has invested in the funding.
+		fn invest_amount(funding_id: Hash, investor: AccountId) -> Balance;
+		/// The investors of the funding and the money each of them invested.
+		fn investments_of_funding(funding_id: Hash) -> Vec<(AccountId, Balance)>;
+		/// The request, `None` if it does not exist.
+		fn request_details(request_id: Hash) -> Option<RequestDetails>;
+		/// The requests of the funding.
+		fn requests_of_funding(funding_id: Hash) -> Vec<RequestDetails>;
+	}
+}
+
 // Implement our runtime API endpoints. This is just a bunch of proxying.
 impl_runtime_apis! {
 	impl runtime_api::Core<Block> for Runtime {
@@ -306,4 +360,44 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl self::CrowdfundingApi<Block> for Runtime {
+		fn funding_details(funding_id: Hash) -> Option<FundingDetails> {
+			Request::funding_details(funding_id)
+		}
+
+		fn list_fundings(offset: u64, limit: u64, status_filter: Option<u64>) -> Vec<FundingDetails> {
+			FundingFactory::list_fundings(offset, limit, status_filter).into_iter()
+				.filter_map(Request::funding_details)
+				.collect()
+		}
+
+		fn fundings_of_owner(owner: AccountId) -> Vec<FundingDetails> {
+			FundingFactory::fundings_of_owner(owner).into_iter()
+				.filter_map(Request::funding_details)
+				.collect()
+		}
+
+		fn fundings_of_investor(investor: AccountId) -> Vec<FundingDetails> {
+			FundingFactory::fundings_of_investor(investor).into_iter()
+				.filter_map(Request::funding_details)
+				.collect()
+		}
+
+		fn invest_amount(funding_id: Hash, investor: AccountId) -> Balance {
+			FundingFactory::get_invest_amount(funding_id, investor)
+		}
+
+		fn investments_of_funding(funding_id: Hash) -> Vec<(AccountId, Balance)> {
+			FundingFactory::investments_of(funding_id)
+		}
+
+		fn request_details(request_id: Hash) -> Option<RequestDetails> {
+			Request::request_details(request_id)
+		}
+
+		fn requests_of_funding(funding_id: Hash) -> Vec<RequestDetails> {
+			Request::requests_of_funding(funding_id)
+		}
+	}
 }
@@ -0,0 +1,382 @@
+use crate::funding_factory;
+use support::{decl_storage, decl_module, StorageValue, StorageMap,
+              dispatch::Result, ensure, decl_event, traits::ReservableCurrency};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::traits::{Hash, Zero};
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Pool<Hash, AccountId, Balance, BlockNumber>{
+    // the only id of a pool
+    pool_id: Hash,
+    // the account whose reserved money the pool pays the matches with
+    sponsor: AccountId,
+    // the investments made from the start to the end block are matched
+    start: BlockNumber,
+    end: BlockNumber,
+    // the maximum money matched for a funding
+    cap: Balance,
+    // the money not matched yet
+    available: Balance,
+}
+
+decl_error! {
+    /// The errors of the module, the codes are stable and must never be reused
+    pub enum MatchingError {
+        PoolEmpty = 400 => "The pool has to hold some money",
+        InvalidRange = 401 => "The end has to be after the start and the current block number",
+        CapZero = 402 => "The cap of a funding has to be greater than zero",
+        NoEligibleFundings = 403 => "The pool has to match at least one funding",
+        TooManyEligibleFundings = 404 => "Maximum number of eligible fundings is reached for the pool",
+        DuplicateFunding = 405 => "The funding is eligible twice",
+        FundingNotRaising = 406 => "The eligible funding is not raising",
+        TooManyPools = 407 => "Maximum number of pools is reached for the funding",
+        InsufficientBalance = 408 => "You don't have enough free balance for the pool",
+        PoolExists = 409 => "Pool already exists",
+        PoolNotExist = 410 => "The pool does not exist",
+        NotSponsor = 411 => "The sender must be the sponsor of the pool",
+        PoolEnded = 412 => "The pool has ended",
+        PoolNotEnded = 413 => "The pool has not ended",
+    }
+}
+
+pub trait Trait: funding_factory::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+const MAX_ELIGIBLE_FUNDINGS: usize = 100;
+const MAX_POOLS_PER_FUNDING: usize = 10;
+
+decl_event!(
+    pub enum Event<T>
+    where
+        <T as system::Trait>::AccountId,
+        <T as system::Trait>::Hash,
+        <T as balances::Trait>::Balance,
+        <T as system::Trait>::BlockNumber
+    {
+        PoolCreated(AccountId, Hash, Balance, BlockNumber, BlockNumber, Balance),
+        PoolToppedUp(Hash, Balance),
+        PoolClosed(Hash, Balance),
+        /// The pool matched the investment of the account in the funding
+        Matched(Hash, Hash, AccountId, Balance),
+        /// The pool paid its matches into the succeeded funding
+        MatchPaid(Hash, Hash, Balance),
+        /// The matches of the failed funding went back to the pool
+        MatchReturned(Hash, Hash, Balance),
+        /// A call failed with the module error, which is encoded as its stable code
+        CallFailed(MatchingError),
+    }
+);
+
+decl_storage! {
+    trait Store for Module<T: Trait> as FundingMatching {
+        // Global state
+        Pools get(pool_by_id): map T::Hash => Option<Pool<T::Hash, T::AccountId, T::Balance, T::BlockNumber>>;
+        // The fundings that the pool matches
+        EligibleFundings get(eligible_fundings_of): map T::Hash => Vec<T::Hash>;
+        // The pools that match the funding
+        PoolsOfFunding get(pools_of_funding): map T::Hash => Vec<T::Hash>;
+        // The money that the pool matched in the funding
+        MatchedOf get(matched_of): map (T::Hash, T::Hash) => T::Balance;
+
+        // Record the number of pools
+        Nonce: u64;
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        // Initializing events
+        fn deposit_event<T>() = default;
+
+        /// Sponsor a pool matching the investments in the eligible fundings 1:1 up to the cap of each funding
+        fn create_pool(origin, amount: T::Balance, start: T::BlockNumber, end: T::BlockNumber, cap: T::Balance, eligible: Vec<T::Hash>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                Self::open_pool(sender, amount, start, end, cap, eligible)
+            })
+        }
+
+        /// Open a pool with the money of the treasury
+        fn create_treasury_pool(origin, amount: T::Balance, start: T::BlockNumber, end: T::BlockNumber, cap: T::Balance, eligible: Vec<T::Hash>) -> Result{
+            Self::reported(|| {
+                ensure_root(origin)?;
+                Self::open_pool(<funding_factory::Module<T>>::treasury_account(), amount, start, end, cap, eligible)
+            })
+        }
+
+        /// Add money to the pool
+        fn top_up_pool(origin, pool_id: T::Hash, amount: T::Balance) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                let mut pool = Self::pool_by_id(&pool_id).ok_or_else(|| Self::failed(MatchingError::PoolNotExist))?;
+                ensure!(pool.sponsor == sender, Self::failed(MatchingError::NotSponsor));
+                ensure!(<system::Module<T>>::block_number() <= pool.end, Self::failed(MatchingError::PoolEnded));
+
+                <balances::Module<T>>::reserve(&sender, amount).map_err(|_| Self::failed(MatchingError::InsufficientBalance))?;
+                pool.available = pool.available + amount;
+                <Pools<T>>::insert(pool_id, pool);
+
+                Self::deposit_event(RawEvent::PoolToppedUp(pool_id, amount));
+                Ok(())
+            })
+        }
+
+        /// Return the money that the ended pool has not matched to its sponsor, anyone can close it
+        fn close_pool(origin, pool_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let _ = ensure_signed(origin)?;
+                let mut pool = Self::pool_by_id(&pool_id).ok_or_else(|| Self::failed(MatchingError::PoolNotExist))?;
+                ensure!(<system::Module<T>>::block_number() > pool.end, Self::failed(MatchingError::PoolNotEnded));
+
+                let available = pool.available;
+                let _ = <balances::Module<T>>::unreserve(&pool.sponsor, available);
+                pool.available = T::Balance::zero();
+                <Pools<T>>::insert(pool_id, pool);
+
+                Self::deposit_event(RawEvent::PoolClosed(pool_id, available));
+                Ok(())
+            })
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    // The message of the error, the dispatchable calls record the code for the clients
+    fn failed(error: MatchingError) -> &'static str{
+        error.as_str()
+    }
+
+    // Run the body of a dispatchable call and record the error that it failed with
+    fn reported<F: FnOnce() -> Result>(call: F) -> Result{
+        call().map_err(|message| {
+            Self::report_failure(message);
+            message
+        })
+    }
+
+    // Record the error of the module, or of the module that it calls which returned it
+    fn report_failure(message: &'static str){
+        if !Self::report(message) {
+            <funding_factory::Module<T>>::report(message);
+        }
+    }
+
+    /// Record the error of the failed call if it is an error of the module, returns false otherwise
+    pub fn report(message: &'static str) -> bool{
+        match MatchingError::from_message(message) {
+            Some(error) => {
+                Self::deposit_event(RawEvent::CallFailed(error));
+                true
+            },
+            None => false,
+        }
+    }
+
+    // The sponsor's money of the pool stays reserved until it is paid or the pool is closed
+    fn open_pool(sponsor: T::AccountId, amount: T::Balance, start: T::BlockNumber, end: T::BlockNumber, cap: T::Balance, eligible: Vec<T::Hash>) -> Result{
+        ensure!(amount > T::Balance::zero(), Self::failed(MatchingError::PoolEmpty));
+        ensure!(start < end && end > <system::Module<T>>::block_number(), Self::failed(MatchingError::InvalidRange));
+        ensure!(cap > T::Balance::zero(), Self::failed(MatchingError::CapZero));
+        ensure!(!eligible.is_empty(), Self::failed(MatchingError::NoEligibleFundings));
+        ensure!(eligible.len() <= MAX_ELIGIBLE_FUNDINGS, Self::failed(MatchingError::TooManyEligibleFundings));
+        for (i, funding_id) in eligible.iter().enumerate() {
+            ensure!(!eligible[..i].contains(funding_id), Self::failed(MatchingError::DuplicateFunding));
+            ensure!(<funding_factory::Module<T>>::is_funding_exists(*funding_id), Self::failed(MatchingError::FundingNotRaising));
+            ensure!(<funding_factory::Module<T>>::is_funding_success(*funding_id) == 0, Self::failed(MatchingError::FundingNotRaising));
+            ensure!(Self::pools_of_funding(funding_id).len() < MAX_POOLS_PER_FUNDING, Self::failed(MatchingError::TooManyPools));
+        }
+
+        let nonce = <Nonce<T>>::get();
+        let pool_id = (<system::Module<T>>::random_seed(), &sponsor, nonce)
+            .using_encoded(<T as system::Trait>::Hashing::hash);
+        ensure!(!<Pools<T>>::exists(&pool_id), Self::failed(MatchingError::PoolExists));
+
+        <balances::Module<T>>::reserve(&sponsor, amount).map_err(|_| Self::failed(MatchingError::InsufficientBalance))?;
+
+        let pool = Pool{
+            pool_id,
+            sponsor: sponsor.clone(),
+            start,
+            end,
+            cap,
+            available: amount,
+        };
+        <Pools<T>>::insert(pool_id, pool);
+        for funding_id in &eligible {
+            <PoolsOfFunding<T>>::mutate(funding_id, |pools| pools.push(pool_id));
+        }
+        <EligibleFundings<T>>::insert(pool_id, eligible);
+        <Nonce<T>>::mutate(|n| *n += 1);
+
+        Self::deposit_event(RawEvent::PoolCreated(sponsor, pool_id, amount, start, end, cap));
+        Ok(())
+    }
+}
+
+impl<T: Trait> funding_factory::OnInvest<T::AccountId, T::Hash, T::Balance> for Module<T> {
+    // Every running pool of the funding matches the investment as far as its cap and its money allow
+    fn on_invest(funding_id: &T::Hash, investor: &T::AccountId, amount: T::Balance) {
+        let block_number = <system::Module<T>>::block_number();
+        for pool_id in Self::pools_of_funding(funding_id) {
+            let mut pool = match Self::pool_by_id(&pool_id) {
+                Some(pool) => pool,
+                None => continue,
+            };
+            if block_number < pool.start || block_number > pool.end {
+                continue;
+            }
+
+            let matched = Self::matched_of((pool_id, *funding_id));
+            let room = if pool.cap > matched { pool.cap - matched } else { T::Balance::zero() };
+            let mut matching = amount;
+            if room < matching {
+                matching = room;
+            }
+            if pool.available < matching {
+                matching = pool.available;
+            }
+            if matching.is_zero() {
+                continue;
+            }
+
+            pool.available = pool.available - matching;
+            <Pools<T>>::insert(pool_id, pool);
+            <MatchedOf<T>>::insert((pool_id, *funding_id), matched + matching);
+            Self::deposit_event(RawEvent::Matched(pool_id, *funding_id, investor.clone(), matching));
+        }
+    }
+}
+
+impl<T: Trait> funding_factory::OnFundingFinalized<T::AccountId, T::Hash, T::Balance> for Module<T> {
+    // The succeeded funding gets the matches from the sponsors, the failed one gives them back to the pools
+    fn on_funding_finalized(funding_id: &T::Hash, owner: &T::AccountId, success: bool) -> T::Balance {
+        let block_number = <system::Module<T>>::block_number();
+        let mut paid = T::Balance::zero();
+        for pool_id in <PoolsOfFunding<T>>::take(funding_id) {
+            let matched = <MatchedOf<T>>::take((pool_id, *funding_id));
+            let mut pool = match Self::pool_by_id(&pool_id) {
+                Some(pool) => pool,
+                None => continue,
+            };
+            if matched.is_zero() {
+                continue;
+            }
+
+            // The part of the match that is not paid goes back to the pool
+            let mut returned = matched;
+            if success {
+                if let Ok(remaining) = <balances::Module<T>>::repatriate_reserved(&pool.sponsor, owner, matched) {
+                    // The sponsor can't pay the part missing from its reserve
+                    let moved = matched - remaining;
+                    if !moved.is_zero() {
+                        paid = paid + moved;
+                        Self::deposit_event(RawEvent::MatchPaid(pool_id, *funding_id, moved));
+                    }
+                    returned = remaining;
+                }
+            }
+            if returned.is_zero() {
+                continue;
+            }
+            // The ended pool returns the match to its sponsor directly
+            if block_number > pool.end {
+                let _ = <balances::Module<T>>::unreserve(&pool.sponsor, returned);
+            } else {
+                pool.available = pool.available + returned;
+                <Pools<T>>::insert(pool_id, pool);
+            }
+            Self::deposit_event(RawEvent::MatchReturned(pool_id, *funding_id, returned));
+        }
+        paid
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    use support::assert_ok;
+    use runtime_io::with_externalities;
+    use primitives::H256;
+    use crate::funding_factory::tests::{FundingTest, Origin, build_ext, run_to_block, last_funding};
+
+    type Fundings = funding_factory::Module<FundingTest>;
+    type Matching = super::Module<FundingTest>;
+    type Balances = balances::Module<FundingTest>;
+
+    // A funding of the manager #1 targeting 1000 and expiring at the block 10, with a pool of #5 matching it
+    // up to 300 with 500 until the block 20
+    fn matched_funding() -> (H256, H256) {
+        assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+        let funding_id = last_funding();
+        assert_ok!(Matching::create_pool(Origin::signed(5), 500, 0, 20, 300, vec![funding_id]));
+        (funding_id, Matching::pools_of_funding(funding_id)[0])
+    }
+
+    fn available(pool_id: H256) -> u64 {
+        Matching::pool_by_id(pool_id).map(|pool| pool.available).unwrap_or_default()
+    }
+
+    #[test]
+    fn matches_should_be_paid_to_the_succeeded_funding() {
+        with_externalities(&mut build_ext(), || {
+            let (funding_id, pool_id) = matched_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 600));
+            assert_ok!(Fundings::invest(Origin::signed(3), funding_id, 400));
+            // the cap stops the matches of the funding
+            assert_eq!(Matching::matched_of((pool_id, funding_id)), 300);
+            assert_eq!(available(pool_id), 200);
+
+            run_to_block(11);
+            assert_eq!(Fundings::is_funding_success(funding_id), 1);
+            assert_eq!(Fundings::matched_of_funding(funding_id), 300);
+            assert_eq!(Fundings::get_funding_total_balance(funding_id), 1300);
+            assert_eq!(Balances::reserved_balance(1), 1300);
+            assert_eq!(Balances::reserved_balance(5), 200);
+            assert_eq!(Matching::matched_of((pool_id, funding_id)), 0);
+            assert!(Matching::pools_of_funding(funding_id).is_empty());
+        })
+    }
+
+    #[test]
+    fn matches_should_pay_only_the_reserved_money() {
+        with_externalities(&mut build_ext(), || {
+            let (funding_id, pool_id) = matched_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 1000));
+            // the sponsor keeps only 100 of the 300 matched reserved
+            Balances::unreserve(&5, 400);
+
+            run_to_block(11);
+            assert_eq!(Fundings::matched_of_funding(funding_id), 100);
+            assert_eq!(Balances::reserved_balance(1), 1100);
+            assert_eq!(Balances::reserved_balance(5), 0);
+            // the 200 not paid go back to the pool
+            assert_eq!(available(pool_id), 400);
+        })
+    }
+
+    #[test]
+    fn matches_should_return_to_the_pool_when_funding_fails() {
+        with_externalities(&mut build_ext(), || {
+            let (funding_id, pool_id) = matched_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 300));
+            assert_eq!(available(pool_id), 200);
+
+            run_to_block(11);
+            assert_eq!(Fundings::is_funding_success(funding_id), 2);
+            assert_eq!(available(pool_id), 500);
+            assert_eq!(Balances::reserved_balance(5), 500);
+            assert!(Matching::pools_of_funding(funding_id).is_empty());
+
+            // the ended pool returns its money to the sponsor
+            run_to_block(21);
+            assert_ok!(Matching::close_pool(Origin::signed(2), pool_id));
+            assert_eq!(available(pool_id), 0);
+            assert_eq!(Balances::reserved_balance(5), 0);
+        })
+    }
+}
@@ -1,8 +1,8 @@
 use crate::funding_factory;
 use support::{decl_storage, decl_module, StorageValue, StorageMap,
               dispatch::Result, ensure, decl_event, traits::{Currency, ReservableCurrency}};
-use system::ensure_signed;
-use runtime_primitives::traits::{As, Hash, Zero};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::{Perbill, Permill, traits::{As, Hash, Zero, CheckedAdd}};
 use parity_codec::{Encode, Decode};
 use rstd::prelude::*;
 
@@ -17,18 +17,142 @@ pub struct Request<Hash, Balance, BlockNumber>{
     purpose: Vec<u8>,
     // needed money
     cost: Balance,
+    // the budget line that the money is spent from
+    budget_line: u32,
     // the request deadline
     expiry: BlockNumber,
-    // status
+    // status 0- Pending 1- Success 2- Failure 3- Cancelled 4- Approved and waiting for execution 5- In dispute
     status: u64,
+    // kind 0- Spending 1- Dissolving the project 2- Reallocating the budget
+    kind: u64,
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Stream<Balance, BlockNumber>{
+    // the block when the money starts vesting
+    start: BlockNumber,
+    // the block when all the money is vested
+    end: BlockNumber,
+    // the money to be vested
+    total: Balance,
+    // the money that the beneficiary has claimed
+    claimed: Balance,
+    // whether the investors halted the stream
+    halted: bool,
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Evidence<Hash, AccountId, Balance>{
+    // the hash of the invoice
+    invoice_hash: Hash,
+    // the IPFS content identifier of the document
+    content_id: Vec<u8>,
+    // the vendor who issued the invoice
+    vendor: AccountId,
+    // the amount of the invoice
+    amount: Balance,
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RequestDetails<Hash, AccountId, Balance, BlockNumber>{
+    pub request_id: Hash,
+    pub funding_id: Hash,
+    pub owner: Option<AccountId>,
+    pub purpose: Vec<u8>,
+    pub cost: Balance,
+    pub budget_line: u32,
+    pub expiry: BlockNumber,
+    pub status: u64,
+    pub kind: u64,
+    // the number of people who support and oppose the request
+    pub supported: u64,
+    pub opposed: u64,
+    // the ratio of the investors required to approve the request
+    pub required_ratio: Permill,
+    pub beneficiaries: Vec<(AccountId, Balance)>,
+}
+
+decl_error! {
+    /// The errors of the module, the codes are stable and must never be reused
+    pub enum RequestError {
+        FundingNotExist = 200 => "The funding does not exist",
+        FundingNotSucceed = 201 => "The funding does not succeed",
+        OwnerNotExist = 202 => "The owner does not exist",
+        NotFundingOwner = 203 => "The sender must be the owner of the funding",
+        ProofMissing = 204 => "The funding has requests missing the proof of spend",
+        ExpiryTooEarly = 205 => "The expiry has to be greater than the current block number",
+        ExpiryTooLate = 206 => "The expiry has be lower than the limit block number",
+        InsufficientReserved = 207 => "The reserved balance of the owner is not enough",
+        InsufficientBond = 208 => "You don't have enough free balance for the bond of the request",
+        NotInvestor = 209 => "You are not the investor",
+        SameBudgetLine = 210 => "The budget lines must be different",
+        NoBudgetLines = 211 => "The funding has no budget lines",
+        RequestNotExist = 212 => "The request does not exist",
+        RequestOver = 213 => "The request is over",
+        RequestExpired = 214 => "This request is expired.",
+        SecretBallot = 215 => "The request is voted by secret ballot",
+        VotedBefore = 216 => "You have voted before",
+        VoteCountOverflow = 217 => "Overflow adding the number of people who have voted the request",
+        HalfNumberError = 218 => "Error when get half of the invested number",
+        NoRequestOwner = 219 => "No owner of the request",
+        NotRequestOwner = 220 => "The sender must be the owner of the request",
+        RequestVoted = 221 => "The request has been voted",
+        NotSpendingRequest = 222 => "Only the spending request can be amended",
+        RequestNotPaid = 223 => "The request is not paid",
+        ProofSubmitted = 224 => "The proof has been submitted",
+        StreamNotExist = 225 => "The stream does not exist",
+        NotBeneficiary = 226 => "You are not the beneficiary",
+        NothingVested = 227 => "There is no vested money to claim",
+        StreamHalted = 228 => "The stream is halted",
+        StreamOver = 229 => "The stream is over",
+        HaltVoteCountOverflow = 230 => "Overflow adding the number of people who have voted to halt the stream",
+        CommitEndTooEarly = 231 => "The commit end has to be greater than the current block number",
+        CommitEndTooLate = 232 => "The commit end has to be lower than the expiry",
+        NotSecretBallot = 233 => "The request is not voted by secret ballot",
+        CommitPhaseOver = 234 => "The commit phase is over",
+        CommitPhaseNotOver = 235 => "The commit phase is not over",
+        NotCommitted = 236 => "You have not committed a vote",
+        CommitmentMismatch = 237 => "The vote does not match the commitment",
+        DelegateToSelf = 238 => "You can't delegate to yourself",
+        NotDelegated = 239 => "You have not delegated",
+        RequestNotApproved = 240 => "The request is not waiting for execution",
+        SupportedRequest = 241 => "You have supported the request",
+        TooManyRequestsLater = 242 => "Maximum number of requests is reached for the target block, try again later",
+        NoBeneficiary = 243 => "The request must have at least one beneficiary",
+        BeneficiaryAmountOverflow = 244 => "Overflow adding the amounts of the beneficiaries",
+        BeneficiaryAmountMismatch = 245 => "The amounts of the beneficiaries must sum up to the cost",
+        InvalidVesting = 246 => "The vesting has to end after it starts",
+        VestingBeneficiaries = 247 => "A vesting request must have only one beneficiary",
+        RemainNotEnough = 248 => "The remain money is not enough",
+        LineRemainNotEnough = 249 => "The remain money of the budget line is not enough",
+        ApprovedTooRecently = 250 => "The funding has approved a request too recently",
+        ReleasedTooMuch = 251 => "The funding has released too much money in the window",
+        RequestExists = 252 => "Request already exists",
+        TooManyRequests = 253 => "Maximum number of requests is reached for the target block, try another block",
+        RequestCountOverflow = 254 => "Overflow adding a new request to total requests",
+        FundingRequestCountOverflow = 255 => "Overflow adding a new request to the funding's requests",
+        OwnerRequestCountOverflow = 256 => "Overflow adding a new request to the owner's requests",
+        FundingNotRunning = 257 => "The funding is not running",
+        ReallocationNotExist = 258 => "The reallocation does not exist",
+        BudgetLineUsed = 259 => "The budget line has used the money",
+        RequestNotDisputed = 260 => "The request is not in dispute",
+        PayeeNotExist = 261 => "The beneficiary account does not exist",
+        ChallengedBefore = 262 => "You have challenged the request before",
+        InsufficientChallengeBond = 263 => "You don't have enough free balance for the bond of the challenge",
+        FundingDisputed = 264 => "The funding is in dispute",
+        PeriodLimitZero = 265 => "The period limit has to be greater than zero",
+        PerBlockCapZero = 266 => "The maximum number of requests in a block has to be greater than zero",
+        FundingFrozen = 267 => "The funding is frozen",
+    }
 }
 
 pub trait Trait: timestamp::Trait + funding_factory::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
-const MAX_REQUESTS_PER_BLOCK: usize = 3;
-
 decl_event!(
     pub enum Event<T>
     where
@@ -39,7 +163,38 @@ decl_event!(
     {
         CreateRequest(AccountId, Hash, Hash, Balance, BlockNumber),
         Vote(AccountId, Hash),
-        RequestFinalized(Hash, u64, BlockNumber, bool),
+        RequestFinalized(Hash, u64, BlockNumber, bool, Permill),
+        RequestCancelled(AccountId, Hash),
+        RequestAmended(AccountId, Hash, Balance),
+        Payout(Hash, AccountId, Balance),
+        StreamClaimed(Hash, AccountId, Balance),
+        HaltVote(AccountId, Hash),
+        StreamHalted(Hash, Balance),
+        RequestApproved(Hash, u64, BlockNumber),
+        RequestChallenged(AccountId, Hash),
+        ChallengeBondReturned(Hash, AccountId, Balance),
+        ChallengeBondSlashed(Hash, AccountId, Balance),
+        Refund(Hash, AccountId, Balance),
+        FundingDissolved(Hash, Balance),
+        Delegated(AccountId, AccountId, Option<Hash>),
+        Undelegated(AccountId, Option<Hash>),
+        SecretBallotEnabled(Hash, BlockNumber),
+        VoteCommitted(AccountId, Hash),
+        VoteRevealed(AccountId, Hash, bool),
+        RequestAutoApproved(Hash, Balance),
+        RejectVote(AccountId, Hash),
+        RequestBondReturned(Hash, Balance),
+        RequestBondSlashed(Hash, Balance),
+        EvidenceAttached(Hash, Hash),
+        ProofSubmitted(Hash, Hash),
+        ProofMissing(Hash, Hash),
+        BudgetReallocated(Hash, u32, u32, Permill),
+        RequestPeriodLimitSet(BlockNumber),
+        MaxRequestsPerBlockSet(u32),
+        /// The unspent money of the funding is refunded to its investors for the reason
+        FundingForceRefunded(Hash, Hash),
+        /// A call failed with the module error, which is encoded as its stable code
+        CallFailed(RequestError),
     }
 );
 
@@ -48,8 +203,35 @@ decl_storage! {
         // Global status
         Requests get(requests): map T::Hash => Request<T::Hash, T::Balance, T::BlockNumber>;
         RequestOwner get(owner_of_request): map T::Hash => Option<T::AccountId>;
+        // The accounts who get paid when the request is approved
+        RequestBeneficiaries get(beneficiaries_of_request): map T::Hash => Vec<(T::AccountId, T::Balance)>;
+        // The block range over which the approved money vests
+        RequestVesting get(vesting_of_request): map T::Hash => Option<(T::BlockNumber, T::BlockNumber)>;
         // Maximum time limit for the request
         RequestPeriodLimit get(request_period_limit) config(): T::BlockNumber = T::BlockNumber::sa(60480);
+        // Maximum number of requests ending in a block
+        MaxRequestsPerBlock get(max_requests_per_block) config(): u32 = 3;
+        // Blocks between the approval and the execution of a request
+        ExecutionDelay get(execution_delay) config(): T::BlockNumber = T::BlockNumber::sa(8640);
+        // The bond that the manager posts for a request in proportion to the cost
+        RequestBondRatio get(request_bond_ratio) config(): Permill = Permill::from_percent(5);
+        // The bond that the manager posted for the request
+        RequestBond get(bond_of_request): map T::Hash => T::Balance;
+        // The part of the bond that the investors get when the manager cancels the request
+        CancellationFee get(cancellation_fee) config(): Permill = Permill::from_percent(20);
+
+        // The invoices and documents that back the request
+        RequestEvidence get(evidence_of_request): map T::Hash => Vec<Evidence<T::Hash, T::AccountId, T::Balance>>;
+        // Maximum time for the manager to submit the proof of spend after the payout
+        ProofPeriod get(proof_period) config(): T::BlockNumber = T::BlockNumber::sa(60480);
+        // The hash of the proof of spend document
+        SpendProof get(proof_of_request): map T::Hash => Option<T::Hash>;
+        // Requests whose proof of spend is due in a block
+        ProofsDueAt get(proofs_due_at): map T::BlockNumber => Vec<T::Hash>;
+        // Judge if the proof of spend of the request is missing
+        MissingProof get(is_proof_missing): map T::Hash => bool;
+        // The number of the funding's requests missing the proof of spend
+        MissingProofCount get(missing_proofs_of_funding): map T::Hash => u64;
 
         // All requests
         AllRequestArray get(request_by_index): map u64 => T::Hash;
@@ -68,15 +250,66 @@ decl_storage! {
 
         // Requests ending in a block
         RequestsByBlockNumber get(request_expire_at): map T::BlockNumber => Vec<T::Hash>;
+        // Approved requests executed in a block
+        PendingExecutions get(execution_at): map T::BlockNumber => Vec<T::Hash>;
+        ExecuteAt get(execute_at_of): map T::Hash => T::BlockNumber;
+        // The status of the disputed request before the dispute
+        StatusBeforeDispute get(status_before_dispute): map T::Hash => u64;
+        // Whether the funding is in an open dispute, its money can not be spent until the dispute is resolved
+        FundingDisputed get(is_funding_disputed): map T::Hash => bool;
+        // The bond that the investor posts to challenge an approved request
+        ChallengeBond get(challenge_bond) config(): T::Balance = T::Balance::sa(1000);
+        // Judge if the investor has challenged the request
+        Challenged get(challenged): map (T::AccountId, T::Hash) => bool;
+        // The challengers of the request and their bonds, slashed if the request is approved again
+        ChallengesOfRequest get(challenges_of_request): map T::Hash => Vec<(T::AccountId, T::Balance)>;
 
         // The amount of money that the project has used
         UsedMoneyOfFunding get(used_money_of_funding): map T::Hash => T::Balance;
+        // The amount of money that the project has used from a budget line
+        UsedMoneyOfLine get(used_money_of_line): map (T::Hash, u32) => T::Balance;
+        // The budget lines and the share that a reallocating request moves
+        Reallocations get(reallocation_of): map T::Hash => Option<(u32, u32, Permill)>;
+        // The block when the last request of the funding was approved
+        LastApprovedAt get(last_approved_at): map T::Hash => Option<T::BlockNumber>;
+        // The money released by the funding in the velocity window
+        ReleasesOfFunding get(releases_of_funding): map T::Hash => Vec<(T::BlockNumber, T::Balance)>;
+        // The start of the current allowance period and the money spent without voting in it
+        AllowanceUsed get(allowance_used_of): map T::Hash => (T::BlockNumber, T::Balance);
 
         // The number of people who support the request
         SupportedOfRequest get(supported_of_request): map T::Hash => u64;
+        // The ratio of the investors that approved the request was required to reach
+        RequiredRatio get(required_ratio_of_request): map T::Hash => Permill;
+        // The number of people who oppose the request
+        OpposedOfRequest get(opposed_of_request): map T::Hash => u64;
 
         // Judge if the user has voted the request
         VotedBefore get(voted_before): map (T::AccountId, T::Hash) => bool;
+        // Judge if the user has voted for the request
+        SupportedBy get(supported_by): map (T::AccountId, T::Hash) => bool;
+        // The investors who have voted the request
+        VotersOfRequest get(voters_of_request): map T::Hash => Vec<T::AccountId>;
+
+        // The block when the commit phase of a secret ballot ends
+        SecretBallot get(commit_end_of): map T::Hash => Option<T::BlockNumber>;
+        // The hash of the vote and the salt that the investor committed
+        Commitments get(commitment_of): map (T::AccountId, T::Hash) => Option<T::Hash>;
+        CommittersOfRequest get(committers_of_request): map T::Hash => Vec<T::AccountId>;
+
+        // The account who votes for the investor on a funding
+        FundingDelegate get(funding_delegate_of): map (T::AccountId, T::Hash) => Option<T::AccountId>;
+        FundingDelegators get(funding_delegators_of): map (T::AccountId, T::Hash) => Vec<T::AccountId>;
+        // The account who votes for the investor on all the fundings
+        GlobalDelegate get(global_delegate_of): map T::AccountId => Option<T::AccountId>;
+        GlobalDelegators get(global_delegators_of): map T::AccountId => Vec<T::AccountId>;
+
+        // The streams of the approved vesting requests
+        Streams get(stream_of): map T::Hash => Option<Stream<T::Balance, T::BlockNumber>>;
+        // The number of people who want to halt the stream
+        HaltVotesOfStream get(halt_votes_of_stream): map T::Hash => u64;
+        // Judge if the user has voted to halt the stream
+        VotedHalt get(voted_halt): map (T::AccountId, T::Hash) => bool;
 
         // Get the status of a request: 1-success 2-failure
 //        RequestStatus get(status_of_request): map T::Hash => u64;
@@ -84,6 +317,23 @@ decl_storage! {
         // Record the number of requests
         Nonce: u64;
     }
+    add_extra_genesis {
+        // The requests created at genesis by the managers: (index of the funding, purpose, cost, expiry, beneficiaries),
+        // the funding has to be finalized with success at genesis
+        config(requests): Vec<(u64, Vec<u8>, T::Balance, T::BlockNumber, Vec<(T::AccountId, T::Balance)>)>;
+
+        build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+            runtime_io::with_storage(storage, || {
+                for (index, purpose, cost, expiry, beneficiaries) in &config.requests {
+                    let funding_id = <funding_factory::Module<T>>::funding_by_index(*index);
+                    let manager = <funding_factory::Module<T>>::get_funding_owner(funding_id)
+                        .expect("The funding of a genesis request has to exist");
+                    <Module<T>>::create_request(system::RawOrigin::Signed(manager).into(), funding_id, purpose.clone(), *cost, 0, *expiry, beneficiaries.clone(), None)
+                        .unwrap_or_else(|e| panic!("Invalid genesis request: {}", e));
+                }
+            });
+        });
+    }
 }
 
 decl_module! {
@@ -91,167 +341,2162 @@ decl_module! {
         // Initializing events
         fn deposit_event<T>() = default;
 
-        fn create_request(origin, funding_id: T::Hash, purpose: Vec<u8>, cost: T::Balance, expiry: T::BlockNumber) -> Result{
-            let sender = ensure_signed(origin)?;
-
-            // Ensure the funding exists
-            ensure!(<funding_factory::Module<T>>::is_funding_exists(funding_id), "The funding does not exist");
-            // Ensure the funding is success
-            ensure!(<funding_factory::Module<T>>::is_funding_success(funding_id) == 1, "The funding does not succeed");
-            // Ensure the sender is the owner
-            let owner = <funding_factory::Module<T>>::get_funding_owner(funding_id).ok_or("The owner does not exist")?;
-            ensure!(sender == owner, "The sender must be the owner of the funding");
-
-            // ensure that the expiry is valid
-            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
-            ensure!(expiry <= <system::Module<T>>::block_number() + Self::request_period_limit(), "The expiry has be lower than the limit block number");
-
-            let used_balance = Self::used_money_of_funding(&funding_id);
-            let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(funding_id);
-            let remain_balance = total_balance - used_balance;
-            ensure!(remain_balance >= cost, "The remain money is not enough");
-            // get the nonce to help generate unique id
-            let nonce = <Nonce<T>>::get();
-
-            // generate the unique id
-            let request_id = (<system::Module<T>>::random_seed(), &cost, &sender, nonce)
-                .using_encoded(<T as system::Trait>::Hashing::hash);
-            // ensure that the request id is unique
-            ensure!(!<Requests<T>>::exists(&funding_id), "Request already exists");
-
-            let new_request = Request{
-                request_id,
-                funding_id: funding_id.clone(),
-                purpose,
-                cost,
-                expiry,
-                status: 0,
-            };
-
-            // ensure that the number of requests in the block does not exceed maximum
-            let requests = Self::request_expire_at(expiry);
-            ensure!(requests.len() < MAX_REQUESTS_PER_BLOCK, "Maximum number of requests is reached for the target block, try another block");
-
-            // Verify adding count is ok first
-            // Check adding all request count
-            let all_request_count = Self::all_request_count();
-            let new_all_request_count = all_request_count.checked_add(1).ok_or("Overflow adding a new request to total requests")?;
-
-            // Check adding requests of funding count
-            let request_of_funding_count = Self::request_of_funding_count(funding_id);
-            let new_request_of_funding_count = request_of_funding_count.checked_add(1).ok_or("Overflow adding a new request to the funding's requests")?;
-
-            // Check adding requests of owner count
-            let request_of_owner_count = Self::request_of_owner_count(&sender);
-            let new_request_of_owner_count = request_of_owner_count.checked_add(1).ok_or("Overflow adding a new request to the owner's requests")?;
-
-            // change the global states
-            <Requests<T>>::insert(request_id.clone(), new_request.clone());
-            <RequestOwner<T>>::insert(request_id.clone(), sender.clone());
-
-            <RequestsByBlockNumber<T>>::mutate(expiry, |requests| requests.push(request_id.clone()));
-
-            // change the state of all requests
-            <AllRequestArray<T>>::insert(&all_request_count, request_id.clone());
-            <AllRequestCount<T>>::put(new_all_request_count);
-            <AllRequestIndex<T>>::insert(request_id.clone(), all_request_count);
-
-            // change the state of funding's requests
-            <RequestOfFundingArray<T>>::insert((funding_id.clone(), request_of_funding_count.clone()), request_id.clone());
-            <RequestOfFundingCount<T>>::insert(funding_id.clone(), new_request_of_funding_count);
-            <RequestOfFundingIndex<T>>::insert((funding_id.clone(), request_id.clone()), request_of_funding_count);
-
-            // change the state of owner's requests
-            <RequestOfOwnerArray<T>>::insert((sender.clone(), request_of_owner_count.clone()), request_id.clone());
-            <RequestOfOwnerCount<T>>::insert(sender.clone(), new_request_of_owner_count);
-            <RequestOfOwnerIndex<T>>::insert((sender.clone(), request_id.clone()), request_of_owner_count);
-
-            // add the nonce
-            <Nonce<T>>::mutate(|n| *n += 1);
-
-            // deposit the event
-            Self::deposit_event(RawEvent::CreateRequest(sender, funding_id, request_id, cost, expiry));
-            Ok(())
+        fn create_request(origin, funding_id: T::Hash, purpose: Vec<u8>, cost: T::Balance, budget_line: u32, expiry: T::BlockNumber, beneficiaries: Vec<(T::AccountId, T::Balance)>, vesting: Option<(T::BlockNumber, T::BlockNumber)>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+
+                // Ensure the funding exists
+                ensure!(<funding_factory::Module<T>>::is_funding_exists(funding_id), Self::failed(RequestError::FundingNotExist));
+                // Ensure the funding is success
+                ensure!(<funding_factory::Module<T>>::is_funding_success(funding_id) == 1, Self::failed(RequestError::FundingNotSucceed));
+                ensure!(!<funding_factory::Module<T>>::is_funding_frozen(funding_id), Self::failed(RequestError::FundingFrozen));
+                // Ensure the sender is the owner
+                let owner = <funding_factory::Module<T>>::get_funding_owner(funding_id).ok_or_else(|| Self::failed(RequestError::OwnerNotExist))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotFundingOwner));
+                // Ensure all the paid requests have the proof of spend
+                ensure!(Self::missing_proofs_of_funding(&funding_id) == 0, Self::failed(RequestError::ProofMissing));
+
+                // ensure that the expiry is valid
+                ensure!(expiry > <system::Module<T>>::block_number(), Self::failed(RequestError::ExpiryTooEarly));
+                ensure!(expiry <= <system::Module<T>>::block_number() + Self::request_period_limit(), Self::failed(RequestError::ExpiryTooLate));
+
+                // Ensure the funding and the budget line have enough money
+                Self::check_remain(funding_id, budget_line, cost)?;
+                // Ensure the funding does not release money too fast
+                Self::check_velocity(funding_id, cost)?;
+                // Ensure the beneficiaries share the cost
+                Self::check_beneficiaries(&beneficiaries, cost)?;
+                Self::check_vesting(&vesting, &beneficiaries)?;
+
+                // Check if the request is under the allowance of the current period
+                let (allowance, allowance_period) = <funding_factory::Module<T>>::get_funding_allowance(funding_id);
+                let mut auto_approved = None;
+                if allowance > T::Balance::zero() {
+                    let block_number = <system::Module<T>>::block_number();
+                    let period_start = block_number - block_number % allowance_period;
+                    let (used_period, used_allowance) = Self::allowance_used_of(&funding_id);
+                    let used_allowance = if used_period == period_start { used_allowance } else { T::Balance::zero() };
+                    if used_allowance + cost <= allowance {
+                        // Ensure the request can be paid before creating it
+                        Self::check_payout(&owner, T::Balance::zero(), cost, &beneficiaries)?;
+                        auto_approved = Some((period_start, used_allowance + cost));
+                    }
+                }
+
+                // The request which needs voting costs the manager a bond
+                let bond = if auto_approved.is_none() { Self::request_bond_ratio() * cost } else { T::Balance::zero() };
+                ensure!(<balances::Module<T>>::can_reserve(&sender, bond.clone()), Self::failed(RequestError::InsufficientBond));
+
+                let request_id = Self::mint(sender.clone(), funding_id, purpose, cost, budget_line, expiry, 0)?;
+                <RequestBeneficiaries<T>>::insert(request_id.clone(), beneficiaries);
+                if let Some(vesting) = vesting {
+                    <RequestVesting<T>>::insert(request_id.clone(), vesting);
+                }
+                if bond > T::Balance::zero() {
+                    <balances::Module<T>>::reserve(&sender, bond.clone())?;
+                    <RequestBond<T>>::insert(request_id.clone(), bond);
+                }
+
+                // deposit the event
+                Self::deposit_event(RawEvent::CreateRequest(sender, funding_id, request_id, cost, expiry));
+
+                // Execute the request under the allowance without voting
+                if let Some(allowance_used) = auto_approved {
+                    <AllowanceUsed<T>>::insert(funding_id, allowance_used);
+                    Self::use_money(funding_id, budget_line, cost);
+                    Self::deposit_event(RawEvent::RequestAutoApproved(request_id.clone(), cost));
+                    Self::execute_request(request_id, 0)?;
+                }
+                Ok(())
+            })
+        }
+
+        /// Propose to dissolve a succeeded funding and refund the remain money to the investors
+        fn propose_dissolve(origin, funding_id: T::Hash, expiry: T::BlockNumber) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+
+                // Ensure the funding exists
+                ensure!(<funding_factory::Module<T>>::is_funding_exists(funding_id), Self::failed(RequestError::FundingNotExist));
+                // Ensure the funding is success
+                ensure!(<funding_factory::Module<T>>::is_funding_success(funding_id) == 1, Self::failed(RequestError::FundingNotSucceed));
+                // Ensure the user is investor
+                ensure!(<funding_factory::Module<T>>::is_investor(funding_id, sender.clone()), Self::failed(RequestError::NotInvestor));
+
+                // ensure that the expiry is valid
+                ensure!(expiry > <system::Module<T>>::block_number(), Self::failed(RequestError::ExpiryTooEarly));
+                ensure!(expiry <= <system::Module<T>>::block_number() + Self::request_period_limit(), Self::failed(RequestError::ExpiryTooLate));
+
+                let request_id = Self::mint(sender.clone(), funding_id, b"dissolve project".to_vec(), T::Balance::zero(), 0, expiry, 1)?;
+
+                // deposit the event
+                Self::deposit_event(RawEvent::CreateRequest(sender, funding_id, request_id, T::Balance::zero(), expiry));
+                Ok(())
+            })
+        }
+
+        /// Propose to move a share of the money from one budget line to another
+        fn propose_reallocation(origin, funding_id: T::Hash, from: u32, to: u32, share: Permill, expiry: T::BlockNumber) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+
+                // Ensure the funding exists
+                ensure!(<funding_factory::Module<T>>::is_funding_exists(funding_id), Self::failed(RequestError::FundingNotExist));
+                // Ensure the funding is success
+                ensure!(<funding_factory::Module<T>>::is_funding_success(funding_id) == 1, Self::failed(RequestError::FundingNotSucceed));
+                // Ensure the sender is the owner
+                let owner = <funding_factory::Module<T>>::get_funding_owner(funding_id).ok_or_else(|| Self::failed(RequestError::OwnerNotExist))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotFundingOwner));
+                // Ensure the budget lines exist
+                ensure!(from != to, Self::failed(RequestError::SameBudgetLine));
+                ensure!(<funding_factory::Module<T>>::get_budget_line_cap(funding_id, from)?.is_some(), Self::failed(RequestError::NoBudgetLines));
+                <funding_factory::Module<T>>::get_budget_line_cap(funding_id, to)?;
+
+                // ensure that the expiry is valid
+                ensure!(expiry > <system::Module<T>>::block_number(), Self::failed(RequestError::ExpiryTooEarly));
+                ensure!(expiry <= <system::Module<T>>::block_number() + Self::request_period_limit(), Self::failed(RequestError::ExpiryTooLate));
+
+                let request_id = Self::mint(sender.clone(), funding_id, b"reallocate budget".to_vec(), T::Balance::zero(), from, expiry, 2)?;
+                <Reallocations<T>>::insert(request_id.clone(), (from, to, share));
+
+                // deposit the event
+                Self::deposit_event(RawEvent::CreateRequest(sender, funding_id, request_id, T::Balance::zero(), expiry));
+                Ok(())
+            })
         }
 
         fn support_request(origin, request_id: T::Hash) -> Result{
-            let sender = ensure_signed(origin)?;
-            // Ensure the request exists
-            ensure!(<Requests<T>>::exists(&request_id), "The request does not exist");
-            // Get the request
-            let mut request = Self::requests(&request_id);
-            // Ensure the user is investor
-            ensure!(<funding_factory::Module<T>>::is_investor(request.funding_id, sender.clone()), "You are not the investor");
-            // Ensure the investor does not vote before
-            ensure!(!<VotedBefore<T>>::get((sender.clone(), request_id.clone())), "You have voted before");
-            // Ensure the request is not over
-            ensure!(request.status == 0, "The request is over");
-            // Ensure the request is not expire
-            ensure!(<system::Module<T>>::block_number() < request.expiry, "This request is expired.");
-            // Get the number of people who have supported the request and add 1
-            let supported_request_count = Self::supported_of_request(&request_id);
-            let new_supported_request_count = supported_request_count.checked_add(1).ok_or("Overflow adding the number of people who have voted the request")?;
-            // Check if the number is bigger than half
-            let invested_number = <funding_factory::Module<T>>::get_invested_number(request.funding_id);
-            let half_number = invested_number.checked_div(2).ok_or("Error when get half of the invested number")?;
-            let supported_count = new_supported_request_count.clone();
-            // If the supported_count is bigger than the half, the request is success
-            if new_supported_request_count > half_number{
-                Self::can_use_balance(request_id, supported_count)?;
-            }
-            // Change the investor voting status
-            <VotedBefore<T>>::insert((sender.clone(), request_id.clone()), true);
-            // Change the number of supporters
-            <SupportedOfRequest<T>>::insert(request_id.clone(), new_supported_request_count.clone());
-            // Deposit the Vote event
-            Self::deposit_event(RawEvent::Vote(sender, request_id.clone()));
-            Ok(())
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Get the request
+                let request = Self::requests(&request_id);
+                ensure!(!<funding_factory::Module<T>>::is_funding_frozen(request.funding_id), Self::failed(RequestError::FundingFrozen));
+                // Ensure the request is not over
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                // Ensure the request is not expire
+                ensure!(<system::Module<T>>::block_number() < request.expiry, Self::failed(RequestError::RequestExpired));
+                // Ensure the request is voted in public
+                ensure!(Self::commit_end_of(&request_id).is_none(), Self::failed(RequestError::SecretBallot));
+                // Get the investor and the delegators who have not voted yet
+                let voters = Self::voters_of(sender.clone(), request.funding_id, request_id);
+                if voters.is_empty() {
+                    // Ensure the user is investor
+                    ensure!(<funding_factory::Module<T>>::is_investor(request.funding_id, sender.clone()), Self::failed(RequestError::NotInvestor));
+                    return Err(Self::failed(RequestError::VotedBefore));
+                }
+                // Get the number of people who have supported the request and add the voters
+                let supported_request_count = Self::supported_of_request(&request_id);
+                let new_supported_request_count = supported_request_count.checked_add(voters.len() as u64).ok_or_else(|| Self::failed(RequestError::VoteCountOverflow))?;
+                // Check if the number reaches the ratio required by the size of the request
+                let invested_number = <funding_factory::Module<T>>::get_invested_number(request.funding_id);
+                let supported_count = new_supported_request_count.clone();
+                // If the supported_count is bigger than the required ratio, the request is success
+                if Self::is_approved(new_supported_request_count, invested_number, Self::required_ratio(&request)){
+                    Self::can_use_balance(request_id, supported_count)?;
+                }
+                // Change the voting status of the investor and the delegators
+                for voter in &voters {
+                    <VotedBefore<T>>::insert((voter.clone(), request_id.clone()), true);
+                    <SupportedBy<T>>::insert((voter.clone(), request_id.clone()), true);
+                }
+                <VotersOfRequest<T>>::mutate(&request_id, |request_voters| request_voters.extend(voters));
+                // Change the number of supporters
+                <SupportedOfRequest<T>>::insert(request_id.clone(), new_supported_request_count.clone());
+                // Deposit the Vote event
+                Self::deposit_event(RawEvent::Vote(sender, request_id.clone()));
+                Ok(())
+            })
+        }
+
+        /// Vote against a request, a supermajority of the investors rejects it and slashes the bond
+        fn reject_request(origin, request_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Get the request
+                let mut request = Self::requests(&request_id);
+                // Ensure the request is not over
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                // Ensure the request is not expire
+                ensure!(<system::Module<T>>::block_number() < request.expiry, Self::failed(RequestError::RequestExpired));
+                // Ensure the request is voted in public
+                ensure!(Self::commit_end_of(&request_id).is_none(), Self::failed(RequestError::SecretBallot));
+                // Get the investor and the delegators who have not voted yet
+                let voters = Self::voters_of(sender.clone(), request.funding_id, request_id);
+                if voters.is_empty() {
+                    // Ensure the user is investor
+                    ensure!(<funding_factory::Module<T>>::is_investor(request.funding_id, sender.clone()), Self::failed(RequestError::NotInvestor));
+                    return Err(Self::failed(RequestError::VotedBefore));
+                }
+                let opposed_count = Self::opposed_of_request(&request_id).checked_add(voters.len() as u64).ok_or_else(|| Self::failed(RequestError::VoteCountOverflow))?;
+
+                // Change the voting status of the investor and the delegators
+                for voter in &voters {
+                    <VotedBefore<T>>::insert((voter.clone(), request_id.clone()), true);
+                }
+                <VotersOfRequest<T>>::mutate(&request_id, |request_voters| request_voters.extend(voters));
+                <OpposedOfRequest<T>>::insert(request_id.clone(), opposed_count);
+                Self::deposit_event(RawEvent::RejectVote(sender, request_id.clone()));
+
+                // If the opposed count is a supermajority, the request is rejected
+                let invested_number = <funding_factory::Module<T>>::get_invested_number(request.funding_id);
+                if Self::is_supermajority(opposed_count, invested_number) {
+                    request.status = 2;
+                    <Requests<T>>::insert(request_id.clone(), request.clone());
+                    Self::settle_request_bond(&request_id, request.funding_id, true);
+                    Self::settle_challenges(&request_id, false);
+                    let supported_count = Self::supported_of_request(&request_id);
+                    let required_ratio = Self::required_ratio(&request);
+                    Self::deposit_event(RawEvent::RequestFinalized(request_id, supported_count, request.expiry, false, required_ratio));
+                }
+                Ok(())
+            })
+        }
+
+        /// Cancel a request which nobody has voted yet
+        fn cancel_request(origin, request_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Ensure the sender is the owner of the request
+                let owner = Self::owner_of_request(&request_id).ok_or_else(|| Self::failed(RequestError::NoRequestOwner))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotRequestOwner));
+                // Get the request
+                let mut request = Self::requests(&request_id);
+                // Ensure the request is not over
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                // Ensure nobody has voted the request
+                ensure!(Self::voters_of_request(&request_id).is_empty(), Self::failed(RequestError::RequestVoted));
+
+                // Free the slot of the expiry block
+                <RequestsByBlockNumber<T>>::mutate(request.expiry, |requests| requests.retain(|id| id != &request_id));
+                // Change the request status
+                request.status = 3;
+                <Requests<T>>::insert(request_id.clone(), request.clone());
+                // The investors keep the cancellation fee out of the bond
+                Self::slash_request_bond(&request_id, request.funding_id, Self::cancellation_fee());
+                Self::settle_challenges(&request_id, false);
+
+                Self::deposit_event(RawEvent::RequestCancelled(sender, request_id));
+                Ok(())
+            })
+        }
+
+        /// Amend the purpose, the cost and the beneficiaries of a request, the votes before are reset
+        fn amend_request(origin, request_id: T::Hash, purpose: Vec<u8>, cost: T::Balance, beneficiaries: Vec<(T::AccountId, T::Balance)>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Ensure the sender is the owner of the request
+                let owner = Self::owner_of_request(&request_id).ok_or_else(|| Self::failed(RequestError::NoRequestOwner))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotRequestOwner));
+                // Get the request
+                let mut request = Self::requests(&request_id);
+                // Ensure the request is not over
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                // Ensure the request is not expire
+                ensure!(<system::Module<T>>::block_number() < request.expiry, Self::failed(RequestError::RequestExpired));
+                // Ensure the request spends money
+                ensure!(request.kind == 0, Self::failed(RequestError::NotSpendingRequest));
+
+                // Ensure the funding and the budget line have enough money
+                Self::check_remain(request.funding_id, request.budget_line, cost)?;
+                // Ensure the beneficiaries share the cost
+                Self::check_beneficiaries(&beneficiaries, cost)?;
+                Self::check_vesting(&Self::vesting_of_request(&request_id), &beneficiaries)?;
+
+                // Change the bond with the cost
+                let bond = Self::bond_of_request(&request_id);
+                let new_bond = Self::request_bond_ratio() * cost;
+                if new_bond > bond {
+                    <balances::Module<T>>::reserve(&sender, new_bond - bond)?;
+                } else {
+                    let _ = <balances::Module<T>>::unreserve(&sender, bond - new_bond);
+                }
+                <RequestBond<T>>::insert(request_id.clone(), new_bond);
+
+                // Reset the votes of the request
+                Self::reset_votes(&request_id);
+
+                // Change the request
+                request.purpose = purpose;
+                request.cost = cost.clone();
+                <Requests<T>>::insert(request_id.clone(), request);
+                <RequestBeneficiaries<T>>::insert(request_id.clone(), beneficiaries);
+
+                Self::deposit_event(RawEvent::RequestAmended(sender, request_id, cost));
+                Ok(())
+            })
+        }
+
+        /// Attach an invoice to a request before it is paid
+        fn attach_evidence(origin, request_id: T::Hash, invoice_hash: T::Hash, content_id: Vec<u8>, vendor: T::AccountId, amount: T::Balance) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Ensure the sender is the owner of the request
+                let owner = Self::owner_of_request(&request_id).ok_or_else(|| Self::failed(RequestError::NoRequestOwner))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotRequestOwner));
+                // Ensure the request is not paid
+                let status = Self::requests(&request_id).status;
+                ensure!(status == 0 || status == 4, Self::failed(RequestError::RequestOver));
+
+                <RequestEvidence<T>>::mutate(&request_id, |evidence| evidence.push(Evidence{
+                    invoice_hash: invoice_hash.clone(),
+                    content_id,
+                    vendor,
+                    amount,
+                }));
+
+                Self::deposit_event(RawEvent::EvidenceAttached(request_id, invoice_hash));
+                Ok(())
+            })
+        }
+
+        /// Submit the hash of the proof of spend document of a paid request
+        fn submit_proof(origin, request_id: T::Hash, document_hash: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Ensure the sender is the owner of the request
+                let owner = Self::owner_of_request(&request_id).ok_or_else(|| Self::failed(RequestError::NoRequestOwner))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotRequestOwner));
+                // Ensure the request is paid
+                let request = Self::requests(&request_id);
+                ensure!(request.status == 1 && request.kind == 0, Self::failed(RequestError::RequestNotPaid));
+                ensure!(Self::proof_of_request(&request_id).is_none(), Self::failed(RequestError::ProofSubmitted));
+
+                <SpendProof<T>>::insert(request_id.clone(), document_hash.clone());
+                // A late proof unblocks the funding
+                if <MissingProof<T>>::take(&request_id) {
+                    <MissingProofCount<T>>::mutate(request.funding_id, |count| *count = count.saturating_sub(1));
+                }
+
+                Self::deposit_event(RawEvent::ProofSubmitted(request_id, document_hash));
+                Ok(())
+            })
+        }
+
+        /// Claim the money of a stream which has vested
+        fn claim_vested(origin, request_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                let mut stream = Self::stream_of(&request_id).ok_or_else(|| Self::failed(RequestError::StreamNotExist))?;
+                // Ensure the sender is the beneficiary of the stream
+                let beneficiaries = Self::beneficiaries_of_request(&request_id);
+                ensure!(beneficiaries.iter().any(|(who, _)| who == &sender), Self::failed(RequestError::NotBeneficiary));
+
+                let vested = Self::vested_of(&stream, <system::Module<T>>::block_number());
+                ensure!(vested > stream.claimed, Self::failed(RequestError::NothingVested));
+                let amount = vested - stream.claimed;
+
+                // Pay the vested money from the owner's reserved balance
+                let funding_id = Self::requests(&request_id).funding_id;
+                let owner = <funding_factory::Module<T>>::get_funding_owner(funding_id).ok_or_else(|| Self::failed(RequestError::OwnerNotExist))?;
+                ensure!(<balances::Module<T>>::reserved_balance(&owner) >= amount, Self::failed(RequestError::InsufficientReserved));
+                Self::pay_reserved(&owner, &sender, amount.clone())?;
+
+                stream.claimed = vested;
+                <Streams<T>>::insert(request_id.clone(), stream);
+
+                Self::deposit_event(RawEvent::StreamClaimed(request_id, sender, amount));
+                Ok(())
+            })
+        }
+
+        /// Vote to halt a stream, the unvested money goes back to the funding
+        fn vote_halt_stream(origin, request_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                let mut stream = Self::stream_of(&request_id).ok_or_else(|| Self::failed(RequestError::StreamNotExist))?;
+                // Ensure the stream is still vesting
+                let block_number = <system::Module<T>>::block_number();
+                ensure!(!stream.halted, Self::failed(RequestError::StreamHalted));
+                ensure!(block_number < stream.end, Self::failed(RequestError::StreamOver));
+                // Ensure the user is investor
+                let request = Self::requests(&request_id);
+                let funding_id = request.funding_id;
+                ensure!(<funding_factory::Module<T>>::is_investor(funding_id, sender.clone()), Self::failed(RequestError::NotInvestor));
+                // Ensure the investor does not vote before
+                ensure!(!Self::voted_halt((sender.clone(), request_id.clone())), Self::failed(RequestError::VotedBefore));
+
+                let halt_votes = Self::halt_votes_of_stream(&request_id);
+                let new_halt_votes = halt_votes.checked_add(1).ok_or_else(|| Self::failed(RequestError::HaltVoteCountOverflow))?;
+                let invested_number = <funding_factory::Module<T>>::get_invested_number(funding_id);
+                let half_number = invested_number.checked_div(2).ok_or_else(|| Self::failed(RequestError::HalfNumberError))?;
+
+                <VotedHalt<T>>::insert((sender.clone(), request_id.clone()), true);
+                <HaltVotesOfStream<T>>::insert(request_id.clone(), new_halt_votes);
+                Self::deposit_event(RawEvent::HaltVote(sender, request_id.clone()));
+
+                // If the halt votes are bigger than the half, stop the stream here
+                if new_halt_votes > half_number {
+                    let vested = Self::vested_of(&stream, block_number);
+                    let unvested = stream.total - vested;
+                    stream.total = vested;
+                    stream.end = block_number;
+                    if stream.start > block_number {
+                        stream.start = block_number;
+                    }
+                    stream.halted = true;
+                    <Streams<T>>::insert(request_id.clone(), stream);
+                    // Give the unvested money back to the funding
+                    Self::free_money(funding_id, request.budget_line, unvested);
+                    Self::deposit_event(RawEvent::StreamHalted(request_id, unvested));
+                }
+                Ok(())
+            })
+        }
+
+        /// Make the investors commit their votes before the commit end and reveal them before the expiry
+        fn enable_secret_ballot(origin, request_id: T::Hash, commit_end: T::BlockNumber) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Ensure the sender is the owner of the request
+                let owner = Self::owner_of_request(&request_id).ok_or_else(|| Self::failed(RequestError::NoRequestOwner))?;
+                ensure!(sender == owner, Self::failed(RequestError::NotRequestOwner));
+                // Get the request
+                let request = Self::requests(&request_id);
+                // Ensure the request is not over
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                // Ensure nobody has voted the request
+                ensure!(Self::voters_of_request(&request_id).is_empty(), Self::failed(RequestError::RequestVoted));
+                // Ensure that the commit end is valid
+                ensure!(commit_end > <system::Module<T>>::block_number(), Self::failed(RequestError::CommitEndTooEarly));
+                ensure!(commit_end < request.expiry, Self::failed(RequestError::CommitEndTooLate));
+
+                <SecretBallot<T>>::insert(request_id.clone(), commit_end.clone());
+
+                Self::deposit_event(RawEvent::SecretBallotEnabled(request_id, commit_end));
+                Ok(())
+            })
+        }
+
+        /// Commit the hash of the encoded vote and salt during the commit phase
+        fn commit_vote(origin, request_id: T::Hash, commitment: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request is in the commit phase
+                let commit_end = Self::commit_end_of(&request_id).ok_or_else(|| Self::failed(RequestError::NotSecretBallot))?;
+                let request = Self::requests(&request_id);
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                ensure!(<system::Module<T>>::block_number() < commit_end, Self::failed(RequestError::CommitPhaseOver));
+                // Ensure the investor or the delegators can vote
+                ensure!(!Self::voters_of(sender.clone(), request.funding_id, request_id).is_empty(), Self::failed(RequestError::NotInvestor));
+
+                if !<Commitments<T>>::exists((sender.clone(), request_id.clone())) {
+                    <CommittersOfRequest<T>>::mutate(&request_id, |committers| committers.push(sender.clone()));
+                }
+                <Commitments<T>>::insert((sender.clone(), request_id.clone()), commitment);
+
+                Self::deposit_event(RawEvent::VoteCommitted(sender, request_id));
+                Ok(())
+            })
+        }
+
+        /// Reveal the committed vote during the reveal phase, the votes not revealed are abstentions
+        fn reveal_vote(origin, request_id: T::Hash, approve: bool, salt: Vec<u8>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request is in the reveal phase
+                let commit_end = Self::commit_end_of(&request_id).ok_or_else(|| Self::failed(RequestError::NotSecretBallot))?;
+                let request = Self::requests(&request_id);
+                let block_number = <system::Module<T>>::block_number();
+                ensure!(request.status == 0, Self::failed(RequestError::RequestOver));
+                ensure!(block_number >= commit_end, Self::failed(RequestError::CommitPhaseNotOver));
+                ensure!(block_number < request.expiry, Self::failed(RequestError::RequestExpired));
+                // Ensure the vote matches the commitment
+                let commitment = Self::commitment_of((sender.clone(), request_id.clone())).ok_or_else(|| Self::failed(RequestError::NotCommitted))?;
+                ensure!((approve, &salt).using_encoded(<T as system::Trait>::Hashing::hash) == commitment, Self::failed(RequestError::CommitmentMismatch));
+
+                // Get the investor and the delegators who have not voted yet
+                let voters = Self::voters_of(sender.clone(), request.funding_id, request_id);
+                ensure!(!voters.is_empty(), Self::failed(RequestError::VotedBefore));
+                let count = voters.len() as u64;
+                if approve {
+                    let supported_count = Self::supported_of_request(&request_id).checked_add(count).ok_or_else(|| Self::failed(RequestError::VoteCountOverflow))?;
+                    <SupportedOfRequest<T>>::insert(request_id.clone(), supported_count);
+                } else {
+                    let opposed_count = Self::opposed_of_request(&request_id).checked_add(count).ok_or_else(|| Self::failed(RequestError::VoteCountOverflow))?;
+                    <OpposedOfRequest<T>>::insert(request_id.clone(), opposed_count);
+                }
+                <Commitments<T>>::remove((sender.clone(), request_id.clone()));
+                // Change the voting status of the investor and the delegators
+                for voter in &voters {
+                    <VotedBefore<T>>::insert((voter.clone(), request_id.clone()), true);
+                    if approve {
+                        <SupportedBy<T>>::insert((voter.clone(), request_id.clone()), true);
+                    }
+                }
+                <VotersOfRequest<T>>::mutate(&request_id, |request_voters| request_voters.extend(voters));
+
+                Self::deposit_event(RawEvent::VoteRevealed(sender, request_id, approve));
+                Ok(())
+            })
+        }
+
+        /// Let another account vote the requests for you, on one funding or on all the fundings
+        fn delegate(origin, to: T::AccountId, funding_id: Option<T::Hash>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                ensure!(sender != to, Self::failed(RequestError::DelegateToSelf));
+
+                // Replace the delegation before
+                Self::remove_delegation(&sender, funding_id);
+                match funding_id {
+                    Some(funding_id) => {
+                        <FundingDelegate<T>>::insert((sender.clone(), funding_id), to.clone());
+                        <FundingDelegators<T>>::mutate((to.clone(), funding_id), |delegators| delegators.push(sender.clone()));
+                    },
+                    None => {
+                        <GlobalDelegate<T>>::insert(&sender, to.clone());
+                        <GlobalDelegators<T>>::mutate(&to, |delegators| delegators.push(sender.clone()));
+                    }
+                }
+
+                Self::deposit_event(RawEvent::Delegated(sender, to, funding_id));
+                Ok(())
+            })
+        }
+
+        /// Revoke the delegation on one funding or on all the fundings
+        fn undelegate(origin, funding_id: Option<T::Hash>) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                ensure!(Self::remove_delegation(&sender, funding_id), Self::failed(RequestError::NotDelegated));
+
+                Self::deposit_event(RawEvent::Undelegated(sender, funding_id));
+                Ok(())
+            })
+        }
+
+        /// Challenge an approved request before it is executed by posting a bond, the investors vote it again
+        fn challenge_request(origin, request_id: T::Hash) -> Result{
+            Self::reported(|| {
+                let sender = ensure_signed(origin)?;
+                // Ensure the request exists
+                ensure!(<Requests<T>>::exists(&request_id), Self::failed(RequestError::RequestNotExist));
+                // Get the request
+                let mut request = Self::requests(&request_id);
+                // Ensure the request is waiting for execution
+                ensure!(request.status == 4, Self::failed(RequestError::RequestNotApproved));
+                // Ensure the user is investor who did not support the request
+                ensure!(<funding_factory::Module<T>>::is_investor(request.funding_id, sender.clone()), Self::failed(RequestError::NotInvestor));
+                ensure!(!Self::supported_by((sender.clone(), request_id.clone())), Self::failed(RequestError::SupportedRequest));
+                // Ensure the investor challenges the request only once
+                ensure!(!Self::challenged((sender.clone(), request_id.clone())), Self::failed(RequestError::ChallengedBefore));
+                // The bond is slashed if the investors approve the request again
+                let bond = Self::challenge_bond();
+                ensure!(<balances::Module<T>>::can_reserve(&sender, bond.clone()), Self::failed(RequestError::InsufficientChallengeBond));
+
+                // The request needs a new expiry if the old one has passed
+                let block_number = <system::Module<T>>::block_number();
+                let new_expiry = if request.expiry > block_number {
+                    None
+                } else {
+                    let expiry = block_number + Self::request_period_limit();
+                    let requests = Self::request_expire_at(expiry);
+                    ensure!(requests.len() < Self::max_requests_per_block() as usize, Self::failed(RequestError::TooManyRequestsLater));
+                    Some(expiry)
+                };
+
+                // Post the bond
+                <balances::Module<T>>::reserve(&sender, bond.clone())?;
+                <Challenged<T>>::insert((sender.clone(), request_id.clone()), true);
+                <ChallengesOfRequest<T>>::mutate(&request_id, |challenges| challenges.push((sender.clone(), bond)));
+
+                // Remove the request from the execution queue
+                let execute_at = Self::execute_at_of(&request_id);
+                <PendingExecutions<T>>::mutate(execute_at, |requests| requests.retain(|id| id != &request_id));
+                <ExecuteAt<T>>::remove(&request_id);
+                // Give the money back to the funding
+                Self::free_money(request.funding_id, request.budget_line, request.cost);
+                // Let the investors vote again in public
+                Self::reset_votes(&request_id);
+                <SecretBallot<T>>::remove(&request_id);
+                if let Some(expiry) = new_expiry {
+                    <RequestsByBlockNumber<T>>::mutate(expiry, |requests| requests.push(request_id.clone()));
+                    request.expiry = expiry;
+                }
+                request.status = 0;
+                <Requests<T>>::insert(request_id.clone(), request);
+
+                Self::deposit_event(RawEvent::RequestChallenged(sender, request_id));
+                Ok(())
+            })
+        }
+
+        /// Change the limits of the requests, the `None` ones are kept
+        fn set_parameters(origin, request_period_limit: Option<T::BlockNumber>, max_requests_per_block: Option<u32>) -> Result{
+            Self::reported(|| {
+                ensure_root(origin)?;
+                if let Some(limit) = request_period_limit {
+                    ensure!(limit > T::BlockNumber::zero(), Self::failed(RequestError::PeriodLimitZero));
+                }
+                ensure!(max_requests_per_block != Some(0), Self::failed(RequestError::PerBlockCapZero));
+
+                if let Some(limit) = request_period_limit {
+                    <RequestPeriodLimit<T>>::put(limit);
+                    Self::deposit_event(RawEvent::RequestPeriodLimitSet(limit));
+                }
+                if let Some(cap) = max_requests_per_block {
+                    <MaxRequestsPerBlock<T>>::put(cap);
+                    Self::deposit_event(RawEvent::MaxRequestsPerBlockSet(cap));
+                }
+                Ok(())
+            })
+        }
+
+        /// Refund the unspent money of the funding to its investors in proportion to their investments,
+        /// the reason is the hash of the report
+        fn force_refund(origin, funding_id: T::Hash, reason: T::Hash) -> Result{
+            Self::reported(|| {
+                ensure_root(origin)?;
+                ensure!(<funding_factory::Module<T>>::is_funding_exists(funding_id), Self::failed(RequestError::FundingNotExist));
+                match <funding_factory::Module<T>>::is_funding_success(funding_id) {
+                    // The raising funding returns the reserved investments
+                    0 => {
+                        let mut total_balance = T::Balance::zero();
+                        for (investor, refund) in <funding_factory::Module<T>>::cancel_raising(funding_id) {
+                            total_balance = total_balance + refund;
+                            Self::deposit_event(RawEvent::Refund(funding_id, investor, refund));
+                        }
+                        Self::deposit_event(RawEvent::FundingDissolved(funding_id, total_balance));
+                    },
+                    1 => {
+                        let owner = <funding_factory::Module<T>>::get_funding_owner(funding_id).ok_or_else(|| Self::failed(RequestError::OwnerNotExist))?;
+                        Self::dissolve_funding(funding_id, owner)?;
+                    },
+                    _ => return Err(Self::failed(RequestError::FundingNotRunning)),
+                }
+                Self::deposit_event(RawEvent::FundingForceRefunded(funding_id, reason));
+                Ok(())
+            })
         }
 
         fn on_finalize() {
             // get all the fundings of the block
             let block_number = <system::Module<T>>::block_number();
+
+            // execute the approved requests whose challenge period is over
+            for request_id in &Self::execution_at(block_number){
+                let mut request = Self::requests(request_id);
+                if request.status != 4{
+                    continue;
+                }
+                <ExecuteAt<T>>::remove(request_id);
+                let supported_count = <SupportedOfRequest<T>>::get(request_id);
+                if Self::execute_request(request_id.clone(), supported_count).is_err() {
+                    // The request fails and its money goes back to the funding, unless the funding has closed
+                    if <funding_factory::Module<T>>::is_funding_success(request.funding_id) == 1 {
+                        Self::free_money(request.funding_id, request.budget_line, request.cost);
+                    }
+                    request.status = 2;
+                    <Requests<T>>::insert(request_id.clone(), request.clone());
+                    Self::settle_challenges(request_id, false);
+                    let required_ratio = Self::required_ratio_of_request(request_id);
+                    Self::deposit_event(RawEvent::RequestFinalized(request.request_id, supported_count, request.expiry, false, required_ratio));
+                }
+                // The request is over, the bond goes back to the manager
+                Self::settle_request_bond(request_id, request.funding_id, false);
+            }
+
+            // flag the paid requests whose proof of spend is missing
+            for request_id in &Self::proofs_due_at(block_number){
+                if Self::proof_of_request(request_id).is_some() {
+                    continue;
+                }
+                let funding_id = Self::requests(request_id).funding_id;
+                <MissingProof<T>>::insert(request_id.clone(), true);
+                <MissingProofCount<T>>::mutate(funding_id, |count| *count += 1);
+                Self::deposit_event(RawEvent::ProofMissing(funding_id, request_id.clone()));
+            }
+
             let request_hashs = Self::request_expire_at(block_number);
 
             for request_id in &request_hashs{
                 // Get the request
                 let mut request = Self::requests(request_id);
-                // Check if the request is success before
-                if request.status == 1{
+                // Check if the request is not under voting
+                if request.status != 0{
                     continue;
                 }
+                // Tally the revealed votes of a secret ballot
+                if Self::commit_end_of(request_id).is_some() {
+                    let supported_count = <SupportedOfRequest<T>>::get(request_id);
+                    let invested_number = <funding_factory::Module<T>>::get_invested_number(request.funding_id);
+                    if Self::is_approved(supported_count, invested_number, Self::required_ratio(&request)) && Self::can_use_balance(request_id.clone(), supported_count).is_ok() {
+                        continue;
+                    }
+                }
                 // Else the request fails
                 request.status = 2;
                 <Requests<T>>::insert(request_id.clone(), request.clone());
+                // The bond is slashed only if a supermajority of the investors rejected the request
+                let invested_number = <funding_factory::Module<T>>::get_invested_number(request.funding_id);
+                let slashed = Self::is_supermajority(Self::opposed_of_request(request_id), invested_number);
+                Self::settle_request_bond(request_id, request.funding_id, slashed);
+                Self::settle_challenges(request_id, false);
                 let supported_count = <SupportedOfRequest<T>>::get(request.request_id);
-                Self::deposit_event(RawEvent::RequestFinalized(request.request_id, supported_count, request.expiry, false));
+                let required_ratio = Self::required_ratio(&request);
+                Self::deposit_event(RawEvent::RequestFinalized(request.request_id, supported_count, request.expiry, false, required_ratio));
             }
         }
     }
 }
 
 impl<T:Trait> Module<T>{
+    // The message of the error, the dispatchable calls record the code for the clients
+    fn failed(error: RequestError) -> &'static str{
+        error.as_str()
+    }
+
+    // Run the body of a dispatchable call and record the error that it failed with
+    fn reported<F: FnOnce() -> Result>(call: F) -> Result{
+        call().map_err(|message| {
+            Self::report_failure(message);
+            message
+        })
+    }
+
+    // Record the error of the module, or of the module that it calls which returned it
+    fn report_failure(message: &'static str){
+        if !Self::report(message) {
+            <funding_factory::Module<T>>::report(message);
+        }
+    }
+
+    /// Record the error of the failed call if it is an error of the module, returns false otherwise
+    pub fn report(message: &'static str) -> bool{
+        match RequestError::from_message(message) {
+            Some(error) => {
+                Self::deposit_event(RawEvent::CallFailed(error));
+                true
+            },
+            None => false,
+        }
+    }
+
+    // The beneficiaries must be named and their amounts must sum up to the cost
+    fn check_beneficiaries(beneficiaries: &Vec<(T::AccountId, T::Balance)>, cost: T::Balance) -> Result{
+        ensure!(!beneficiaries.is_empty(), Self::failed(RequestError::NoBeneficiary));
+        let mut total = T::Balance::zero();
+        for (_, amount) in beneficiaries {
+            total = total.checked_add(amount).ok_or_else(|| Self::failed(RequestError::BeneficiaryAmountOverflow))?;
+        }
+        ensure!(total == cost, Self::failed(RequestError::BeneficiaryAmountMismatch));
+        Ok(())
+    }
+
+    // A stream vests over a valid block range and pays only one beneficiary
+    fn check_vesting(vesting: &Option<(T::BlockNumber, T::BlockNumber)>, beneficiaries: &Vec<(T::AccountId, T::Balance)>) -> Result{
+        if let Some((start, end)) = vesting {
+            ensure!(start < end, Self::failed(RequestError::InvalidVesting));
+            ensure!(beneficiaries.len() == 1, Self::failed(RequestError::VestingBeneficiaries));
+        }
+        Ok(())
+    }
+
+    // The money of the stream that has vested linearly at the block
+    fn vested_of(stream: &Stream<T::Balance, T::BlockNumber>, block_number: T::BlockNumber) -> T::Balance{
+        if block_number <= stream.start {
+            return T::Balance::zero();
+        }
+        if block_number >= stream.end {
+            return stream.total;
+        }
+        let elapsed = T::Balance::sa((block_number - stream.start).as_());
+        let duration = T::Balance::sa((stream.end - stream.start).as_());
+        stream.total * elapsed / duration
+    }
+
+    fn mint(sender: T::AccountId, funding_id: T::Hash, purpose: Vec<u8>, cost: T::Balance, budget_line: u32, expiry: T::BlockNumber, kind: u64) -> rstd::result::Result<T::Hash, &'static str>{
+        // get the nonce to help generate unique id
+        let nonce = <Nonce<T>>::get();
+
+        // generate the unique id
+        let request_id = (<system::Module<T>>::random_seed(), &cost, &sender, nonce)
+            .using_encoded(<T as system::Trait>::Hashing::hash);
+        // ensure that the request id is unique
+        ensure!(!<Requests<T>>::exists(&funding_id), Self::failed(RequestError::RequestExists));
+
+        let new_request = Request{
+            request_id,
+            funding_id: funding_id.clone(),
+            purpose,
+            cost,
+            budget_line,
+            expiry,
+            status: 0,
+            kind,
+        };
+
+        // ensure that the number of requests in the block does not exceed maximum
+        let requests = Self::request_expire_at(expiry);
+        ensure!(requests.len() < Self::max_requests_per_block() as usize, Self::failed(RequestError::TooManyRequests));
+
+        // Verify adding count is ok first
+        // Check adding all request count
+        let all_request_count = Self::all_request_count();
+        let new_all_request_count = all_request_count.checked_add(1).ok_or_else(|| Self::failed(RequestError::RequestCountOverflow))?;
+
+        // Check adding requests of funding count
+        let request_of_funding_count = Self::request_of_funding_count(funding_id);
+        let new_request_of_funding_count = request_of_funding_count.checked_add(1).ok_or_else(|| Self::failed(RequestError::FundingRequestCountOverflow))?;
+
+        // Check adding requests of owner count
+        let request_of_owner_count = Self::request_of_owner_count(&sender);
+        let new_request_of_owner_count = request_of_owner_count.checked_add(1).ok_or_else(|| Self::failed(RequestError::OwnerRequestCountOverflow))?;
+
+        // change the global states
+        <Requests<T>>::insert(request_id.clone(), new_request.clone());
+        <RequestOwner<T>>::insert(request_id.clone(), sender.clone());
+
+        <RequestsByBlockNumber<T>>::mutate(expiry, |requests| requests.push(request_id.clone()));
+
+        // change the state of all requests
+        <AllRequestArray<T>>::insert(&all_request_count, request_id.clone());
+        <AllRequestCount<T>>::put(new_all_request_count);
+        <AllRequestIndex<T>>::insert(request_id.clone(), all_request_count);
+
+        // change the state of funding's requests
+        <RequestOfFundingArray<T>>::insert((funding_id.clone(), request_of_funding_count.clone()), request_id.clone());
+        <RequestOfFundingCount<T>>::insert(funding_id.clone(), new_request_of_funding_count);
+        <RequestOfFundingIndex<T>>::insert((funding_id.clone(), request_id.clone()), request_of_funding_count);
+
+        // change the state of owner's requests
+        <RequestOfOwnerArray<T>>::insert((sender.clone(), request_of_owner_count.clone()), request_id.clone());
+        <RequestOfOwnerCount<T>>::insert(sender.clone(), new_request_of_owner_count);
+        <RequestOfOwnerIndex<T>>::insert((sender.clone(), request_id.clone()), request_of_owner_count);
+
+        // add the nonce
+        <Nonce<T>>::mutate(|n| *n += 1);
+
+        Ok(request_id)
+    }
+
     fn can_use_balance(request_id: T::Hash, supported_count: u64) -> Result{
         // Get the request
         let mut request = Self::requests(&request_id);
         let request_balance = request.cost;
         // Ensure that there is enough money
-        let used_balance = <UsedMoneyOfFunding<T>>::get(request.funding_id);
-        let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(request.funding_id);
-        let remain_balance = total_balance - used_balance.clone();
-        ensure!(remain_balance >= request_balance, "The remain balance is not enough");
-        // Get the owner of the funding
-        let owner = <RequestOwner<T>>::get(&request_id).ok_or("No owner of the request")?;
-        // Unreserve the request balance
-        let _ = <balances::Module<T>>::unreserve(&owner, request_balance.clone());
+        Self::check_remain(request.funding_id, request.budget_line, request_balance)?;
+        // Ensure the funding does not release money too fast
+        if request.kind == 0 {
+            Self::check_velocity(request.funding_id, request_balance)?;
+        }
+
+        // Ensure the request can be executed right away before changing anything
+        let delay = Self::execution_delay();
+        if delay == T::BlockNumber::zero() {
+            Self::check_execution(&request_id)?;
+        }
+
+        // The request is approved
+        <RequiredRatio<T>>::insert(request_id.clone(), Self::required_ratio(&request));
         // Change the used amount
-        let new_used_balance = used_balance + request_balance;
-        <UsedMoneyOfFunding<T>>::insert(request.funding_id, new_used_balance);
+        Self::use_money(request.funding_id, request.budget_line, request_balance);
+
+        if delay == T::BlockNumber::zero() {
+            Self::execute_request(request_id.clone(), supported_count)?;
+            // The bond goes back to the manager and the challenges before failed
+            Self::settle_request_bond(&request_id, request.funding_id, false);
+            Self::settle_challenges(&request_id, true);
+            return Ok(());
+        }
+
+        // Queue the request until the challenge period is over, the manager keeps the bond posted until the execution
+        Self::settle_challenges(&request_id, true);
+        let execute_at = <system::Module<T>>::block_number() + delay;
+        <PendingExecutions<T>>::mutate(execute_at.clone(), |requests| requests.push(request_id.clone()));
+        <ExecuteAt<T>>::insert(request_id.clone(), execute_at.clone());
+        // Change the request status
+        request.status = 4;
+        <Requests<T>>::insert(request_id.clone(), request);
+        Self::deposit_event(RawEvent::RequestApproved(request_id, supported_count, execute_at));
+        Ok(())
+    }
+
+    // The funding and the budget line must have enough money left for the cost
+    fn check_remain(funding_id: T::Hash, budget_line: u32, cost: T::Balance) -> Result{
+        ensure!(!Self::is_funding_disputed(&funding_id), Self::failed(RequestError::FundingDisputed));
+        let used_balance = Self::used_money_of_funding(&funding_id);
+        let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(funding_id);
+        let remain_balance = total_balance - used_balance;
+        ensure!(remain_balance >= cost, Self::failed(RequestError::RemainNotEnough));
+        if let Some(cap) = <funding_factory::Module<T>>::get_budget_line_cap(funding_id, budget_line)? {
+            let used_of_line = Self::used_money_of_line((funding_id, budget_line));
+            ensure!(cap >= used_of_line + cost, Self::failed(RequestError::LineRemainNotEnough));
+        }
+        Ok(())
+    }
+
+    // Count the money as used by the funding and the budget line
+    fn use_money(funding_id: T::Hash, budget_line: u32, amount: T::Balance){
+        <UsedMoneyOfFunding<T>>::mutate(funding_id, |used| *used = *used + amount);
+        <UsedMoneyOfLine<T>>::mutate((funding_id, budget_line), |used| *used = *used + amount);
+    }
+
+    // Give the money back to the funding and the budget line
+    fn free_money(funding_id: T::Hash, budget_line: u32, amount: T::Balance){
+        <UsedMoneyOfFunding<T>>::mutate(funding_id, |used| *used = *used - amount);
+        <UsedMoneyOfLine<T>>::mutate((funding_id, budget_line), |used| *used = *used - amount);
+    }
+
+    // The approvals of the funding must be far enough apart and release at most a share of the money in the window
+    fn check_velocity(funding_id: T::Hash, cost: T::Balance) -> Result{
+        let (min_interval, window, max_share) = match <funding_factory::Module<T>>::get_velocity_rule(funding_id) {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+        let block_number = <system::Module<T>>::block_number();
+        if let Some(last_approved_at) = Self::last_approved_at(&funding_id) {
+            ensure!(block_number >= last_approved_at + min_interval, Self::failed(RequestError::ApprovedTooRecently));
+        }
+        let mut released = cost;
+        for (released_at, amount) in Self::releases_of_funding(&funding_id) {
+            if released_at + window > block_number {
+                released = released + amount;
+            }
+        }
+        let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(funding_id);
+        ensure!(released <= max_share * total_balance, Self::failed(RequestError::ReleasedTooMuch));
+        Ok(())
+    }
+
+    // Record the executed release and forget the releases out of the window
+    fn record_release(funding_id: T::Hash, amount: T::Balance){
+        let block_number = <system::Module<T>>::block_number();
+        <LastApprovedAt<T>>::insert(funding_id, block_number);
+        if let Some((_, window, _)) = <funding_factory::Module<T>>::get_velocity_rule(funding_id) {
+            <ReleasesOfFunding<T>>::mutate(funding_id, |releases| {
+                releases.retain(|(released_at, _)| *released_at + window > block_number);
+                releases.push((block_number, amount));
+            });
+        }
+    }
+
+    // Run every check of the execution, the owner of the funding pays the request if they pass
+    fn check_execution(request_id: &T::Hash) -> rstd::result::Result<T::AccountId, &'static str>{
+        let request = Self::requests(request_id);
+        // Ensure the funding is still running
+        ensure!(<funding_factory::Module<T>>::is_funding_success(request.funding_id) == 1, Self::failed(RequestError::FundingNotRunning));
+        // Get the owner of the funding
+        let owner = <funding_factory::Module<T>>::get_funding_owner(request.funding_id).ok_or_else(|| Self::failed(RequestError::OwnerNotExist))?;
+        match request.kind {
+            1 => {
+                Self::check_dissolve(request.funding_id, &owner)?;
+            },
+            2 => {
+                Self::check_reallocate(request_id, request.funding_id)?;
+            },
+            // Ensure the owner still holds the money of the request and the beneficiaries can get it
+            _ => Self::check_payout(&owner, Self::bond_of_request(request_id), request.cost, &Self::beneficiaries_of_request(request_id))?,
+        }
+        Ok(owner)
+    }
+
+    // Pay an approved request whose money is already counted as used
+    fn execute_request(request_id: T::Hash, supported_count: u64) -> Result{
+        let owner = Self::check_execution(&request_id)?;
+        // Get the request
+        let mut request = Self::requests(&request_id);
+        let request_balance = request.cost;
+        match (request.kind, Self::vesting_of_request(&request_id)) {
+            // Refund the remain money to the investors
+            (1, _) => Self::dissolve_funding(request.funding_id, owner)?,
+            // Move the share between the budget lines
+            (2, _) => Self::reallocate(request_id.clone(), request.funding_id)?,
+            // Keep the money reserved and let the beneficiary claim it when vested
+            (_, Some((start, end))) => {
+                <Streams<T>>::insert(request_id.clone(), Stream{
+                    start,
+                    end,
+                    total: request_balance.clone(),
+                    claimed: T::Balance::zero(),
+                    halted: false,
+                });
+            },
+            // Pay the reserved money to the beneficiaries directly
+            (_, None) => {
+                for (beneficiary, amount) in Self::beneficiaries_of_request(&request_id) {
+                    if amount.is_zero() {
+                        continue;
+                    }
+                    Self::pay_reserved(&owner, &beneficiary, amount.clone())?;
+                    Self::deposit_event(RawEvent::Payout(request_id.clone(), beneficiary, amount));
+                }
+            }
+        }
+        // The money is released and the manager has to prove the spending before the deadline
+        if request.kind == 0 {
+            Self::record_release(request.funding_id, request_balance);
+            let deadline = <system::Module<T>>::block_number() + Self::proof_period();
+            <ProofsDueAt<T>>::mutate(deadline, |requests| requests.push(request_id.clone()));
+        }
         // Change the request status
         request.status = 1;
         <Requests<T>>::insert(request_id.clone(), request.clone());
-        Self::deposit_event(RawEvent::RequestFinalized(request_id, supported_count, request.expiry, true));
+        let required_ratio = Self::required_ratio_of_request(&request_id);
+        Self::deposit_event(RawEvent::RequestFinalized(request_id, supported_count, request.expiry, true, required_ratio));
+        Ok(())
+    }
+
+    // The owner holds the cost besides the bond and every beneficiary can receive the money
+    fn check_payout(owner: &T::AccountId, bond: T::Balance, cost: T::Balance, beneficiaries: &Vec<(T::AccountId, T::Balance)>) -> Result{
+        ensure!(<balances::Module<T>>::reserved_balance(owner) >= cost + bond, Self::failed(RequestError::InsufficientReserved));
+        for (beneficiary, amount) in beneficiaries {
+            // The balances module only repatriates to the accounts which exist
+            ensure!(beneficiary == owner || amount.is_zero() || !<balances::Module<T> as Currency<_>>::total_balance(beneficiary).is_zero(),
+                Self::failed(RequestError::PayeeNotExist));
+        }
+        Ok(())
+    }
+
+    // Move the reserved money of the owner to the payee, a part left behind fails the payment
+    fn pay_reserved(owner: &T::AccountId, payee: &T::AccountId, amount: T::Balance) -> Result{
+        let remaining = <balances::Module<T>>::repatriate_reserved(owner, payee, amount)?;
+        ensure!(remaining.is_zero(), Self::failed(RequestError::InsufficientReserved));
+        Ok(())
+    }
+
+    // Return the bonds of the challengers, or slash them as the investors approved the request again
+    fn settle_challenges(request_id: &T::Hash, slashed: bool){
+        for (challenger, bond) in <ChallengesOfRequest<T>>::take(request_id) {
+            if slashed {
+                let _ = <balances::Module<T>>::slash_reserved(&challenger, bond.clone());
+                Self::deposit_event(RawEvent::ChallengeBondSlashed(request_id.clone(), challenger, bond));
+            } else {
+                let _ = <balances::Module<T>>::unreserve(&challenger, bond.clone());
+                Self::deposit_event(RawEvent::ChallengeBondReturned(request_id.clone(), challenger, bond));
+            }
+        }
+    }
+
+    // The queued spending requests and the unvested money of the open streams of the funding,
+    // returns them with the money that they still hold
+    fn cancellable_of_funding(funding_id: T::Hash) -> (Vec<T::Hash>, Vec<T::Hash>, T::Balance){
+        let block_number = <system::Module<T>>::block_number();
+        let mut queued = Vec::new();
+        let mut streams = Vec::new();
+        let mut held = T::Balance::zero();
+        for index in 0..Self::request_of_funding_count(funding_id) {
+            let request_id = Self::request_of_funding_by_index((funding_id, index));
+            let request = Self::requests(&request_id);
+            if request.kind == 0 && request.status == 4 {
+                held = held + request.cost;
+                queued.push(request_id);
+            } else if let Some(stream) = Self::stream_of(&request_id) {
+                if !stream.halted && block_number < stream.end {
+                    held = held + stream.total - Self::vested_of(&stream, block_number);
+                    streams.push(request_id);
+                }
+            }
+        }
+        (queued, streams, held)
+    }
+
+    // The owner still holds the remain money of the funding and every investor can get the refund,
+    // returns the remain money and the refunds
+    fn check_dissolve(funding_id: T::Hash, owner: &T::AccountId) -> rstd::result::Result<(T::Balance, Vec<(T::AccountId, T::Balance)>), &'static str>{
+        let (_, _, held) = Self::cancellable_of_funding(funding_id);
+        let remain_balance = <funding_factory::Module<T>>::get_funding_total_balance(funding_id) - Self::used_money_of_funding(&funding_id) + held;
+        ensure!(<balances::Module<T>>::reserved_balance(owner) >= remain_balance, Self::failed(RequestError::InsufficientReserved));
+
+        // The investments add up to the raised money before the success fee
+        let raised = <funding_factory::Module<T>>::total_amount_of_funding(funding_id);
+        if raised.is_zero() {
+            return Ok((remain_balance, Vec::new()));
+        }
+        let mut refunds = Vec::new();
+        for investor in <funding_factory::Module<T>>::get_invest_accounts(funding_id) {
+            let invest_balance = <funding_factory::Module<T>>::get_invest_amount(funding_id, investor.clone());
+            let refund = Perbill::from_rational_approximation(invest_balance, raised) * remain_balance;
+            ensure!(&investor == owner || refund.is_zero() || !<balances::Module<T> as Currency<_>>::total_balance(&investor).is_zero(),
+                Self::failed(RequestError::PayeeNotExist));
+            refunds.push((investor, refund));
+        }
+        Ok((remain_balance, refunds))
+    }
+
+    // Refund the remain money of the funding to the investors pro-rata and close the funding,
+    // the queued spending requests are cancelled and the open streams are halted
+    pub fn dissolve_funding(funding_id: T::Hash, owner: T::AccountId) -> Result{
+        let (remain_balance, refunds) = Self::check_dissolve(funding_id, &owner)?;
+        let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(funding_id);
+
+        let (queued, streams, _) = Self::cancellable_of_funding(funding_id);
+        for request_id in queued {
+            let mut request = Self::requests(&request_id);
+            let execute_at = Self::execute_at_of(&request_id);
+            <PendingExecutions<T>>::mutate(execute_at, |requests| requests.retain(|id| id != &request_id));
+            <ExecuteAt<T>>::remove(&request_id);
+            request.status = 3;
+            <Requests<T>>::insert(request_id.clone(), request);
+            Self::settle_request_bond(&request_id, funding_id, false);
+            Self::settle_challenges(&request_id, false);
+            Self::deposit_event(RawEvent::RequestCancelled(owner.clone(), request_id));
+        }
+        let block_number = <system::Module<T>>::block_number();
+        for request_id in streams {
+            if let Some(mut stream) = Self::stream_of(&request_id) {
+                let vested = Self::vested_of(&stream, block_number);
+                let unvested = stream.total - vested;
+                stream.total = vested;
+                stream.end = block_number;
+                if stream.start > block_number {
+                    stream.start = block_number;
+                }
+                stream.halted = true;
+                <Streams<T>>::insert(request_id.clone(), stream);
+                Self::deposit_event(RawEvent::StreamHalted(request_id, unvested));
+            }
+        }
+
+        // Nobody invested the funding, the owner keeps the money
+        if refunds.is_empty() {
+            let _ = <balances::Module<T>>::unreserve(&owner, remain_balance.clone());
+        }
+        for (investor, refund) in refunds {
+            // If the investor is owner, just unreserve the money
+            if investor == owner {
+                let _ = <balances::Module<T>>::unreserve(&owner, refund.clone());
+            } else if !refund.is_zero() {
+                Self::pay_reserved(&owner, &investor, refund.clone())?;
+            }
+            Self::deposit_event(RawEvent::Refund(funding_id, investor, refund));
+        }
+
+        // All the money of the funding is gone
+        <UsedMoneyOfFunding<T>>::insert(funding_id, total_balance);
+        <funding_factory::Module<T>>::set_funding_dissolved(funding_id);
+        Self::deposit_event(RawEvent::FundingDissolved(funding_id, remain_balance));
+        Ok(())
+    }
+
+    // The line that gives the share away must still cover the money it has used
+    fn check_reallocate(request_id: &T::Hash, funding_id: T::Hash) -> rstd::result::Result<(u32, u32, Permill), &'static str>{
+        let (from, to, share) = Self::reallocation_of(request_id).ok_or_else(|| Self::failed(RequestError::ReallocationNotExist))?;
+        let cap = <funding_factory::Module<T>>::get_budget_line_cap(funding_id, from)?.ok_or_else(|| Self::failed(RequestError::NoBudgetLines))?;
+        let moved = share * <funding_factory::Module<T>>::get_funding_total_balance(funding_id);
+        ensure!(cap >= Self::used_money_of_line((funding_id, from)) + moved, Self::failed(RequestError::BudgetLineUsed));
+        <funding_factory::Module<T>>::check_reallocate_budget(funding_id, from, to, share)?;
+        Ok((from, to, share))
+    }
+
+    // Move the share between the budget lines of the approved request
+    fn reallocate(request_id: T::Hash, funding_id: T::Hash) -> Result{
+        let (from, to, share) = Self::check_reallocate(&request_id, funding_id)?;
+        <funding_factory::Module<T>>::reallocate_budget(funding_id, from, to, share)?;
+        Self::deposit_event(RawEvent::BudgetReallocated(request_id, from, to, share));
+        Ok(())
+    }
+
+    // The investor can vote the request only once
+    fn can_vote(who: &T::AccountId, funding_id: T::Hash, request_id: T::Hash) -> bool{
+        <funding_factory::Module<T>>::is_investor(funding_id, who.clone()) && !<VotedBefore<T>>::get((who.clone(), request_id))
+    }
+
+    // The account itself and the delegators whose votes are cast with the account's vote
+    fn voters_of(who: T::AccountId, funding_id: T::Hash, request_id: T::Hash) -> Vec<T::AccountId>{
+        let mut voters = Vec::new();
+        if Self::can_vote(&who, funding_id, request_id) {
+            voters.push(who.clone());
+        }
+        for delegator in Self::funding_delegators_of((who.clone(), funding_id)) {
+            if Self::can_vote(&delegator, funding_id, request_id) && !voters.contains(&delegator) {
+                voters.push(delegator);
+            }
+        }
+        // The delegation on the funding overrides the global one
+        for delegator in Self::global_delegators_of(&who) {
+            if Self::funding_delegate_of((delegator.clone(), funding_id)).is_none()
+                && Self::can_vote(&delegator, funding_id, request_id) && !voters.contains(&delegator) {
+                voters.push(delegator);
+            }
+        }
+        voters
+    }
+
+    // Remove the delegation of the account, return false if there is no delegation
+    fn remove_delegation(who: &T::AccountId, funding_id: Option<T::Hash>) -> bool{
+        match funding_id {
+            Some(funding_id) => match <FundingDelegate<T>>::take((who.clone(), funding_id)) {
+                Some(delegate) => {
+                    <FundingDelegators<T>>::mutate((delegate, funding_id), |delegators| delegators.retain(|d| d != who));
+                    true
+                },
+                None => false,
+            },
+            None => match <GlobalDelegate<T>>::take(who) {
+                Some(delegate) => {
+                    <GlobalDelegators<T>>::mutate(&delegate, |delegators| delegators.retain(|d| d != who));
+                    true
+                },
+                None => false,
+            },
+        }
+    }
+
+    // The funding with the money that its requests have not used
+    pub fn funding_details(funding_id: T::Hash) -> Option<funding_factory::FundingDetails<T::Hash, T::AccountId, T::Balance, T::BlockNumber>>{
+        let mut details = <funding_factory::Module<T>>::funding_details(funding_id)?;
+        if details.status == 1 {
+            details.remaining = <funding_factory::Module<T>>::get_funding_total_balance(funding_id) - Self::used_money_of_funding(&funding_id);
+        }
+        Some(details)
+    }
+
+    // The request with its votes, the pending one requires the ratio of its current share of the money
+    pub fn request_details(request_id: T::Hash) -> Option<RequestDetails<T::Hash, T::AccountId, T::Balance, T::BlockNumber>>{
+        if !<Requests<T>>::exists(&request_id) {
+            return None;
+        }
+        let request = Self::requests(&request_id);
+        let required_ratio = if request.status == 0 {
+            Self::required_ratio(&request)
+        } else {
+            Self::required_ratio_of_request(&request_id)
+        };
+        Some(RequestDetails{
+            request_id,
+            funding_id: request.funding_id,
+            owner: Self::owner_of_request(&request_id),
+            purpose: request.purpose,
+            cost: request.cost,
+            budget_line: request.budget_line,
+            expiry: request.expiry,
+            status: request.status,
+            kind: request.kind,
+            supported: Self::supported_of_request(&request_id),
+            opposed: Self::opposed_of_request(&request_id),
+            required_ratio,
+            beneficiaries: Self::beneficiaries_of_request(&request_id),
+        })
+    }
+
+    // All the requests of the funding with their votes
+    pub fn requests_of_funding(funding_id: T::Hash) -> Vec<RequestDetails<T::Hash, T::AccountId, T::Balance, T::BlockNumber>>{
+        (0..Self::request_of_funding_count(funding_id))
+            .filter_map(|index| Self::request_details(Self::request_of_funding_by_index((funding_id, index))))
+            .collect()
+    }
+
+    pub fn get_request_funding(request_id: T::Hash) -> Option<T::Hash>{
+        if <Requests<T>>::exists(&request_id) {
+            Some(Self::requests(&request_id).funding_id)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_request_status(request_id: T::Hash) -> u64{
+        Self::requests(&request_id).status
+    }
+
+    // Hold the request until the dispute is resolved
+    pub fn set_request_disputed(request_id: T::Hash){
+        let mut request = Self::requests(&request_id);
+        // Remove the request from the execution queue and give the money back to the funding
+        if request.status == 4 {
+            let execute_at = Self::execute_at_of(&request_id);
+            <PendingExecutions<T>>::mutate(execute_at, |requests| requests.retain(|id| id != &request_id));
+            <ExecuteAt<T>>::remove(&request_id);
+            Self::free_money(request.funding_id, request.budget_line, request.cost);
+        }
+        <StatusBeforeDispute<T>>::insert(request_id.clone(), request.status);
+        request.status = 5;
+        <Requests<T>>::insert(request_id, request);
+    }
+
+    // Execute the disputed request right away as the arbitrators released it
+    pub fn release_disputed_request(request_id: T::Hash) -> Result{
+        let request = Self::requests(&request_id);
+        ensure!(request.status == 5, Self::failed(RequestError::RequestNotDisputed));
+        Self::check_remain(request.funding_id, request.budget_line, request.cost)?;
+        Self::check_execution(&request_id)?;
+        <StatusBeforeDispute<T>>::remove(&request_id);
+        Self::use_money(request.funding_id, request.budget_line, request.cost);
+        let supported_count = Self::supported_of_request(&request_id);
+        Self::execute_request(request_id.clone(), supported_count)?;
+        Self::settle_request_bond(&request_id, request.funding_id, false);
+        Self::settle_challenges(&request_id, true);
         Ok(())
     }
+
+    // Put the disputed request back as it was before the dispute as the arbitrators did not decide
+    pub fn restore_disputed_request(request_id: T::Hash) -> Result{
+        let mut request = Self::requests(&request_id);
+        ensure!(request.status == 5, Self::failed(RequestError::RequestNotDisputed));
+        let block_number = <system::Module<T>>::block_number();
+        if Self::status_before_dispute(&request_id) == 4 {
+            // Queue the approved request again, the challenge period starts over
+            Self::check_remain(request.funding_id, request.budget_line, request.cost)?;
+            let delay = Self::execution_delay();
+            let execute_at = block_number + if delay == T::BlockNumber::zero() { T::BlockNumber::sa(1) } else { delay };
+            Self::use_money(request.funding_id, request.budget_line, request.cost);
+            <PendingExecutions<T>>::mutate(execute_at.clone(), |requests| requests.push(request_id.clone()));
+            <ExecuteAt<T>>::insert(request_id.clone(), execute_at);
+            request.status = 4;
+        } else {
+            // The pending request needs a new expiry if the old one has passed
+            if request.expiry <= block_number {
+                let expiry = block_number + Self::request_period_limit();
+                ensure!(Self::request_expire_at(expiry).len() < Self::max_requests_per_block() as usize, Self::failed(RequestError::TooManyRequestsLater));
+                <RequestsByBlockNumber<T>>::mutate(expiry, |requests| requests.push(request_id.clone()));
+                request.expiry = expiry;
+            }
+            request.status = 0;
+        }
+        <StatusBeforeDispute<T>>::remove(&request_id);
+        <Requests<T>>::insert(request_id, request);
+        Ok(())
+    }
+
+    // Fail the disputed request as the arbitrators rejected it
+    pub fn reject_disputed_request(request_id: T::Hash){
+        let mut request = Self::requests(&request_id);
+        if request.status != 5 {
+            return;
+        }
+        <StatusBeforeDispute<T>>::remove(&request_id);
+        request.status = 2;
+        <Requests<T>>::insert(request_id.clone(), request.clone());
+        Self::settle_request_bond(&request_id, request.funding_id, false);
+        Self::settle_challenges(&request_id, false);
+        let supported_count = Self::supported_of_request(&request_id);
+        let required_ratio = Self::required_ratio(&request);
+        Self::deposit_event(RawEvent::RequestFinalized(request_id, supported_count, request.expiry, false, required_ratio));
+    }
+
+    // The ratio of the investors that has to support the request, growing with its share of the remain money,
+    // dissolving and reallocating move the whole remain money and require the top ratio
+    fn required_ratio(request: &Request<T::Hash, T::Balance, T::BlockNumber>) -> Permill{
+        let used_balance = Self::used_money_of_funding(&request.funding_id);
+        let total_balance = <funding_factory::Module<T>>::get_funding_total_balance(request.funding_id);
+        let remain_balance = total_balance - used_balance;
+        let share = if request.kind != 0 || remain_balance == T::Balance::zero() {
+            1_000_000
+        } else {
+            (request.cost * T::Balance::sa(1_000_000) / remain_balance).as_()
+        };
+        let curve = <funding_factory::Module<T>>::get_approval_curve(request.funding_id);
+        for (bound, ratio) in &curve {
+            if share < *bound * 1_000_000u64 {
+                return *ratio;
+            }
+        }
+        curve.last().map(|(_, ratio)| *ratio).unwrap_or(Permill::from_percent(50))
+    }
+
+    // More than the ratio of the investors support the request
+    fn is_approved(supported_count: u64, invested_number: u64, ratio: Permill) -> bool{
+        supported_count.saturating_mul(1_000_000) > (ratio * 1_000_000u64).saturating_mul(invested_number)
+    }
+
+    // Hold the money of the funding while the funding is in dispute
+    pub fn set_funding_disputed(funding_id: T::Hash, disputed: bool){
+        if disputed {
+            <FundingDisputed<T>>::insert(funding_id, true);
+        } else {
+            <FundingDisputed<T>>::remove(funding_id);
+        }
+    }
+
+    // More than two thirds of the investors
+    fn is_supermajority(count: u64, invested_number: u64) -> bool{
+        count.saturating_mul(3) > invested_number.saturating_mul(2)
+    }
+
+    // Return the bond of the request to the manager, or share it out to the investors if it is slashed
+    fn settle_request_bond(request_id: &T::Hash, funding_id: T::Hash, slashed: bool){
+        let part = if slashed { Permill::from_percent(100) } else { Permill::from_percent(0) };
+        Self::slash_request_bond(request_id, funding_id, part);
+    }
+
+    // Share the part of the bond out to the investors pro-rata and return the rest to the manager
+    fn slash_request_bond(request_id: &T::Hash, funding_id: T::Hash, part: Permill){
+        let bond = <RequestBond<T>>::take(request_id);
+        if bond == T::Balance::zero() {
+            return;
+        }
+        let owner = match Self::owner_of_request(request_id) {
+            Some(owner) => owner,
+            None => return,
+        };
+        // The bond is returned if nobody invested the money to share it
+        let slash = part * bond;
+        let raised = <funding_factory::Module<T>>::total_amount_of_funding(funding_id);
+        if slash.is_zero() || raised.is_zero() {
+            let _ = <balances::Module<T>>::unreserve(&owner, bond.clone());
+            Self::deposit_event(RawEvent::RequestBondReturned(request_id.clone(), bond));
+            return;
+        }
+
+        let mut paid = T::Balance::zero();
+        for investor in <funding_factory::Module<T>>::get_invest_accounts(funding_id) {
+            if investor == owner {
+                continue;
+            }
+            let invest_balance = <funding_factory::Module<T>>::get_invest_amount(funding_id, investor.clone());
+            let share = Perbill::from_rational_approximation(invest_balance, raised) * slash;
+            if let Ok(remaining) = <balances::Module<T>>::repatriate_reserved(&owner, &investor, share.clone()) {
+                paid = paid + share - remaining;
+            }
+        }
+        // The owner's own share, the rounding dust and the part not slashed go back to the owner
+        let _ = <balances::Module<T>>::unreserve(&owner, bond - paid);
+        Self::deposit_event(RawEvent::RequestBondSlashed(request_id.clone(), paid));
+    }
+
+    // Reset the votes of a request so that the investors can vote it again
+    fn reset_votes(request_id: &T::Hash) {
+        for voter in Self::voters_of_request(request_id) {
+            <VotedBefore<T>>::remove((voter.clone(), request_id.clone()));
+            <SupportedBy<T>>::remove((voter, request_id.clone()));
+        }
+        <VotersOfRequest<T>>::remove(request_id);
+        <SupportedOfRequest<T>>::remove(request_id);
+        <OpposedOfRequest<T>>::remove(request_id);
+        for committer in Self::committers_of_request(request_id) {
+            <Commitments<T>>::remove((committer, request_id.clone()));
+        }
+        <CommittersOfRequest<T>>::remove(request_id);
+    }
 }
 
+#[cfg(test)]
+pub mod tests{
+    use super::*;
+
+    use support::{assert_ok, assert_noop, assert_err};
+    use runtime_io::with_externalities;
+    use primitives::H256;
+    use crate::funding_factory::tests::{FundingTest, Origin, build_ext, run_to_block, last_funding, succeeded_funding};
+
+    type Fundings = funding_factory::Module<FundingTest>;
+    type Requests = super::Module<FundingTest>;
+    type Balances = balances::Module<FundingTest>;
+
+    // The request created last
+    pub fn last_request() -> H256 {
+        Requests::request_by_index(Requests::all_request_count() - 1)
+    }
+
+    // A request of the manager #1 paying the whole cost to #4 and expiring at the block 100
+    pub fn spending_request(funding_id: H256, cost: u64) -> H256 {
+        assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), cost, 0, 100, vec![(4, cost)], None));
+        last_request()
+    }
+
+    #[test]
+    fn cancel_request_should_work() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 500);
+            // the manager holds the money of the funding and the bond of the request
+            assert_eq!(Balances::reserved_balance(1), 1025);
+
+            assert_err!(Requests::cancel_request(Origin::signed(2), request_id), "The sender must be the owner of the request");
+            assert_ok!(Requests::cancel_request(Origin::signed(1), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 3);
+            assert!(Requests::request_expire_at(100).is_empty());
+            // the investors share the cancellation fee pro-rata and the rest of the bond goes back
+            assert_eq!(Balances::reserved_balance(1), 1000);
+            assert_eq!(Balances::free_balance(1), 99995);
+            assert_eq!(Balances::free_balance(2), 99403);
+            assert_eq!(Balances::free_balance(3), 99602);
+            // the request can be cancelled only once
+            assert_err!(Requests::cancel_request(Origin::signed(1), request_id), "The request is over");
+        })
+    }
+
+    #[test]
+    fn voted_request_cannot_be_cancelled() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+
+            assert_err!(Requests::cancel_request(Origin::signed(1), request_id), "The request has been voted");
+            assert_eq!(Requests::get_request_status(request_id), 0);
+        })
+    }
+
+    #[test]
+    fn amend_request_should_reset_votes() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+
+            assert_err!(Requests::amend_request(Origin::signed(2), request_id, b"more tools".to_vec(), 200, vec![(4, 200)]), "The sender must be the owner of the request");
+            assert_err!(Requests::amend_request(Origin::signed(1), request_id, b"more tools".to_vec(), 200, vec![(4, 100)]), "The amounts of the beneficiaries must sum up to the cost");
+            assert_ok!(Requests::amend_request(Origin::signed(1), request_id, b"more tools".to_vec(), 200, vec![(4, 200)]));
+
+            // the bond follows the cost and the investors vote again
+            assert_eq!(Requests::bond_of_request(request_id), 10);
+            assert_eq!(Balances::reserved_balance(1), 1010);
+            assert_eq!(Requests::supported_of_request(request_id), 0);
+            assert!(!Requests::voted_before((2, request_id)));
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+        })
+    }
+
+    #[test]
+    fn approved_request_should_pay_every_beneficiary() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = succeeded_funding();
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 60), (5, 40)], None));
+            let request_id = last_request();
+
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 1);
+            assert_eq!(Balances::free_balance(4), 100060);
+            assert_eq!(Balances::free_balance(5), 100040);
+            // the cost leaves the funding
+            assert_eq!(Balances::reserved_balance(1), 900);
+        })
+    }
+
+    #[test]
+    fn payout_to_missing_account_should_fail() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = succeeded_funding();
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 60), (42, 40)], None));
+            let request_id = last_request();
+
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_err!(Requests::support_request(Origin::signed(3), request_id), "The beneficiary account does not exist");
+            // nobody is paid a part of the request
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_eq!(Balances::free_balance(4), 100000);
+            assert_eq!(Balances::total_balance(&42), 0);
+        })
+    }
+
+    // A request of the manager #1 streaming 100 to #4 from the block 20 to the block 120, approved at once
+    fn approved_stream(funding_id: H256) -> H256 {
+        <ExecutionDelay<FundingTest>>::put(0);
+        assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"salary".to_vec(), 100, 0, 100, vec![(4, 100)], Some((20, 120))));
+        let request_id = last_request();
+        assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+        assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+        assert_eq!(Requests::get_request_status(request_id), 1);
+        request_id
+    }
+
+    #[test]
+    fn claim_vested_should_work() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = approved_stream(funding_id);
+            assert_err!(Requests::claim_vested(Origin::signed(4), request_id), "There is no vested money to claim");
+
+            run_to_block(70);
+            assert_err!(Requests::claim_vested(Origin::signed(5), request_id), "You are not the beneficiary");
+            assert_ok!(Requests::claim_vested(Origin::signed(4), request_id));
+            assert_eq!(Balances::free_balance(4), 100050);
+            assert_eq!(Balances::reserved_balance(1), 950);
+            assert_err!(Requests::claim_vested(Origin::signed(4), request_id), "There is no vested money to claim");
+
+            run_to_block(200);
+            assert_ok!(Requests::claim_vested(Origin::signed(4), request_id));
+            assert_eq!(Balances::free_balance(4), 100100);
+        })
+    }
+
+    #[test]
+    fn claim_vested_should_not_pay_a_part() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = approved_stream(funding_id);
+            // the manager lost the money of the funding
+            let _ = Balances::unreserve(&1, 990);
+
+            run_to_block(70);
+            assert_err!(Requests::claim_vested(Origin::signed(4), request_id), "The reserved balance of the owner is not enough");
+            assert_eq!(Balances::free_balance(4), 100000);
+            assert_eq!(Requests::stream_of(request_id).map(|stream| stream.claimed), Some(0));
+        })
+    }
+
+    #[test]
+    fn halted_stream_should_stop_vesting() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = approved_stream(funding_id);
+
+            run_to_block(70);
+            assert_err!(Requests::vote_halt_stream(Origin::signed(4), request_id), "You are not the investor");
+            assert_ok!(Requests::vote_halt_stream(Origin::signed(2), request_id));
+            assert_err!(Requests::vote_halt_stream(Origin::signed(2), request_id), "You have voted before");
+            assert_ok!(Requests::vote_halt_stream(Origin::signed(3), request_id));
+            // the unvested half goes back to the funding
+            assert_eq!(Requests::used_money_of_funding(funding_id), 50);
+
+            run_to_block(200);
+            assert_ok!(Requests::claim_vested(Origin::signed(4), request_id));
+            assert_eq!(Balances::free_balance(4), 100050);
+        })
+    }
+
+    #[test]
+    fn failed_execution_should_not_approve_the_request() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            // the manager lost the money of the funding
+            let _ = Balances::unreserve(&1, 950);
+
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_err!(Requests::support_request(Origin::signed(3), request_id), "The reserved balance of the owner is not enough");
+            // nothing of the approval is recorded
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_eq!(Requests::used_money_of_funding(funding_id), 0);
+            assert_eq!(Requests::bond_of_request(request_id), 5);
+            assert!(!Requests::voted_before((3, request_id)));
+        })
+    }
+
+    // A funding of the manager #1 invested 600 by #2, 300 by #3 and 100 by #5 and succeeded at the block 10
+    fn funding_of_three() -> H256 {
+        assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+        let funding_id = crate::funding_factory::tests::last_funding();
+        assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 600));
+        assert_ok!(Fundings::invest(Origin::signed(3), funding_id, 300));
+        assert_ok!(Fundings::invest(Origin::signed(5), funding_id, 100));
+        run_to_block(11);
+        funding_id
+    }
+
+    #[test]
+    fn approved_request_should_wait_for_the_challenge_period() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 4);
+            assert_eq!(Requests::execute_at_of(request_id), 8651);
+            // the bond stays posted until the execution
+            assert_eq!(Balances::reserved_balance(1), 1005);
+
+            run_to_block(8651);
+            assert_eq!(Balances::free_balance(4), 100000);
+            run_to_block(8652);
+            assert_eq!(Requests::get_request_status(request_id), 1);
+            assert_eq!(Balances::free_balance(4), 100100);
+            assert_eq!(Balances::reserved_balance(1), 900);
+        })
+    }
+
+    #[test]
+    fn failed_challenge_should_slash_the_bond() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = funding_of_three();
+            let request_id = spending_request(funding_id, 50);
+            assert_ok!(Requests::reject_request(Origin::signed(5), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 4);
+
+            assert_err!(Requests::challenge_request(Origin::signed(7), request_id), "You are not the investor");
+            assert_err!(Requests::challenge_request(Origin::signed(2), request_id), "You have supported the request");
+            <ChallengeBond<FundingTest>>::put(200000);
+            assert_err!(Requests::challenge_request(Origin::signed(5), request_id), "You don't have enough free balance for the bond of the challenge");
+            <ChallengeBond<FundingTest>>::put(1000);
+
+            // the investor who voted against the request challenges it
+            assert_ok!(Requests::challenge_request(Origin::signed(5), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_eq!(Requests::used_money_of_funding(funding_id), 0);
+            assert_eq!(Balances::reserved_balance(5), 1000);
+
+            // the investors approve the request again
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 4);
+            assert_eq!(Balances::reserved_balance(5), 0);
+            assert_eq!(Balances::free_balance(5), 98900);
+            assert_err!(Requests::challenge_request(Origin::signed(5), request_id), "You have challenged the request before");
+        })
+    }
+
+    #[test]
+    fn successful_challenge_should_return_the_bond() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = funding_of_three();
+            let request_id = spending_request(funding_id, 50);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_ok!(Requests::challenge_request(Origin::signed(5), request_id));
+
+            // nobody approves the request again before the expiry
+            run_to_block(101);
+            assert_eq!(Requests::get_request_status(request_id), 2);
+            assert_eq!(Balances::reserved_balance(5), 0);
+            assert_eq!(Balances::free_balance(5), 99900);
+        })
+    }
+
+    #[test]
+    fn dissolve_should_refund_the_remain_money() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+
+            assert_err!(Requests::propose_dissolve(Origin::signed(4), funding_id, 100), "You are not the investor");
+            assert_ok!(Requests::propose_dissolve(Origin::signed(2), funding_id, 100));
+            let dissolve_id = last_request();
+            assert_ok!(Requests::support_request(Origin::signed(2), dissolve_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), dissolve_id));
+
+            // the 900 left are refunded pro-rata
+            assert_eq!(Fundings::is_funding_success(funding_id), 3);
+            assert_eq!(Balances::free_balance(2), 99940);
+            assert_eq!(Balances::free_balance(3), 99960);
+            assert_eq!(Balances::reserved_balance(1), 0);
+        })
+    }
+
+    #[test]
+    fn dissolve_should_cancel_the_queued_requests() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 4);
+
+            // the queued money is refunded with the rest and the bond goes back to the manager
+            assert_ok!(Requests::force_refund(Origin::ROOT, funding_id, H256::zero()));
+            assert_eq!(Requests::get_request_status(request_id), 3);
+            assert_eq!(Balances::reserved_balance(1), 0);
+            assert_eq!(Balances::free_balance(2), 100000);
+            assert_eq!(Balances::free_balance(3), 100000);
+
+            // nothing is paid or given back at the execution block
+            run_to_block(8652);
+            assert_eq!(Requests::get_request_status(request_id), 3);
+            assert_eq!(Balances::free_balance(4), 100000);
+            assert_eq!(Requests::used_money_of_funding(funding_id), 1000);
+        })
+    }
+
+    #[test]
+    fn dissolve_without_investments_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 0, 0, 10, 0, 0, vec![], None, vec![]));
+            let funding_id = crate::funding_factory::tests::last_funding();
+            run_to_block(11);
+            assert_eq!(Fundings::is_funding_success(funding_id), 1);
+
+            assert_ok!(Requests::force_refund(Origin::ROOT, funding_id, H256::zero()));
+            assert_eq!(Fundings::is_funding_success(funding_id), 3);
+        })
+    }
+
+    #[test]
+    fn delegate_should_vote_for_the_delegators() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_err!(Requests::delegate(Origin::signed(3), 3, Some(funding_id)), "You can't delegate to yourself");
+            assert_err!(Requests::undelegate(Origin::signed(3), None), "You have not delegated");
+            assert_ok!(Requests::delegate(Origin::signed(3), 2, Some(funding_id)));
+
+            // the vote of #2 counts for #3 as well
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_eq!(Requests::supported_of_request(request_id), 2);
+            assert_eq!(Requests::get_request_status(request_id), 4);
+            assert_err!(Requests::reject_request(Origin::signed(3), request_id), "The request is over");
+        })
+    }
+
+    #[test]
+    fn undelegated_investor_should_vote_alone() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::delegate(Origin::signed(3), 2, None));
+            assert_ok!(Requests::undelegate(Origin::signed(3), None));
+
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_eq!(Requests::supported_of_request(request_id), 1);
+            assert_ok!(Requests::reject_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::opposed_of_request(request_id), 1);
+        })
+    }
+
+    fn commitment_of_vote(approve: bool, salt: &Vec<u8>) -> H256 {
+        (approve, salt).using_encoded(<FundingTest as system::Trait>::Hashing::hash)
+    }
+
+    #[test]
+    fn secret_ballot_should_tally_the_revealed_votes() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_err!(Requests::enable_secret_ballot(Origin::signed(1), request_id, 100), "The commit end has to be lower than the expiry");
+            assert_ok!(Requests::enable_secret_ballot(Origin::signed(1), request_id, 50));
+            assert_err!(Requests::support_request(Origin::signed(2), request_id), "The request is voted by secret ballot");
+
+            let salt = b"salt".to_vec();
+            assert_ok!(Requests::commit_vote(Origin::signed(2), request_id, commitment_of_vote(true, &salt)));
+            assert_ok!(Requests::commit_vote(Origin::signed(3), request_id, commitment_of_vote(true, &salt)));
+            assert_err!(Requests::reveal_vote(Origin::signed(2), request_id, true, salt.clone()), "The commit phase is not over");
+
+            run_to_block(50);
+            assert_err!(Requests::commit_vote(Origin::signed(2), request_id, commitment_of_vote(false, &salt)), "The commit phase is over");
+            assert_err!(Requests::reveal_vote(Origin::signed(2), request_id, false, salt.clone()), "The vote does not match the commitment");
+            assert_ok!(Requests::reveal_vote(Origin::signed(2), request_id, true, salt.clone()));
+            assert_ok!(Requests::reveal_vote(Origin::signed(3), request_id, true, salt.clone()));
+            // the votes are counted at the expiry
+            assert_eq!(Requests::get_request_status(request_id), 0);
+
+            run_to_block(101);
+            assert_eq!(Requests::get_request_status(request_id), 4);
+        })
+    }
+
+    #[test]
+    fn unrevealed_votes_should_not_count() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::enable_secret_ballot(Origin::signed(1), request_id, 50));
+            let salt = b"salt".to_vec();
+            assert_err!(Requests::commit_vote(Origin::signed(4), request_id, commitment_of_vote(true, &salt)), "You are not the investor");
+            assert_ok!(Requests::commit_vote(Origin::signed(2), request_id, commitment_of_vote(true, &salt)));
+            assert_ok!(Requests::commit_vote(Origin::signed(3), request_id, commitment_of_vote(true, &salt)));
+
+            run_to_block(50);
+            assert_ok!(Requests::reveal_vote(Origin::signed(2), request_id, true, salt.clone()));
+            assert_err!(Requests::reveal_vote(Origin::signed(2), request_id, true, salt.clone()), "You have not committed a vote");
+
+            run_to_block(101);
+            assert_eq!(Requests::get_request_status(request_id), 2);
+        })
+    }
+
+    #[test]
+    fn request_under_the_allowance_should_be_paid_at_once() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 150, 100, vec![], None, vec![]));
+            let funding_id = crate::funding_factory::tests::last_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 1000));
+            run_to_block(11);
+
+            let request_id = spending_request(funding_id, 100);
+            assert_eq!(Requests::get_request_status(request_id), 1);
+            assert_eq!(Balances::free_balance(4), 100100);
+            assert_eq!(Requests::bond_of_request(request_id), 0);
+
+            // the request over the rest of the allowance needs voting
+            let request_id = spending_request(funding_id, 100);
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_eq!(Requests::bond_of_request(request_id), 5);
+        })
+    }
+
+    #[test]
+    fn request_under_the_allowance_should_not_be_created_if_it_can_not_be_paid() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 150, 100, vec![], None, vec![]));
+            let funding_id = crate::funding_factory::tests::last_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 1000));
+            run_to_block(11);
+
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(42, 100)], None), "The beneficiary account does not exist");
+            assert_eq!(Requests::all_request_count(), 0);
+            assert_eq!(Requests::used_money_of_funding(funding_id), 0);
+        })
+    }
+
+    #[test]
+    fn rejected_request_should_slash_the_bond() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_eq!(Requests::bond_of_request(request_id), 5);
+
+            assert_ok!(Requests::reject_request(Origin::signed(2), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_ok!(Requests::reject_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 2);
+            // the investors share the bond pro-rata
+            assert_eq!(Balances::free_balance(2), 99403);
+            assert_eq!(Balances::free_balance(3), 99602);
+            assert_eq!(Balances::reserved_balance(1), 1000);
+        })
+    }
+
+    #[test]
+    fn expired_request_should_return_the_bond() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::reject_request(Origin::signed(2), request_id));
+
+            // a low turnout does not slash the bond
+            run_to_block(101);
+            assert_eq!(Requests::get_request_status(request_id), 2);
+            assert_eq!(Requests::bond_of_request(request_id), 0);
+            assert_eq!(Balances::reserved_balance(1), 1000);
+            assert_eq!(Balances::free_balance(1), 100000);
+        })
+    }
+
+    #[test]
+    fn evidence_should_be_attached_before_the_payout() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            let invoice_hash = H256::repeat_byte(1);
+            assert_err!(Requests::attach_evidence(Origin::signed(2), request_id, invoice_hash, b"Qm".to_vec(), 4, 100), "The sender must be the owner of the request");
+            assert_ok!(Requests::attach_evidence(Origin::signed(1), request_id, invoice_hash, b"Qm".to_vec(), 4, 100));
+            assert_eq!(Requests::evidence_of_request(request_id).len(), 1);
+
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_err!(Requests::attach_evidence(Origin::signed(1), request_id, invoice_hash, b"Qm".to_vec(), 4, 100), "The request is over");
+        })
+    }
+
+    #[test]
+    fn missing_proof_should_block_new_requests() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            <ProofPeriod<FundingTest>>::put(10);
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            let document_hash = H256::repeat_byte(2);
+            assert_err!(Requests::submit_proof(Origin::signed(1), request_id, document_hash), "The request is not paid");
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+
+            // the proof is due at the block 21
+            run_to_block(22);
+            assert!(Requests::is_proof_missing(request_id));
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 100)], None), "The funding has requests missing the proof of spend");
+
+            // a late proof unblocks the funding
+            assert_ok!(Requests::submit_proof(Origin::signed(1), request_id, document_hash));
+            assert_err!(Requests::submit_proof(Origin::signed(1), request_id, document_hash), "The proof has been submitted");
+            assert_eq!(Requests::missing_proofs_of_funding(funding_id), 0);
+            spending_request(funding_id, 100);
+        })
+    }
+
+    // A funding of the manager #1 with 30% of the money for tools and 70% for rent, invested 1000 by #2
+    fn funding_with_budget() -> H256 {
+        let budget = vec![(b"tools".to_vec(), Permill::from_percent(30)), (b"rent".to_vec(), Permill::from_percent(70))];
+        assert_err!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![budget[0].clone()], None, vec![]), "The shares of the budget lines must sum up to 100%");
+        assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, budget, None, vec![]));
+        let funding_id = crate::funding_factory::tests::last_funding();
+        assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 1000));
+        run_to_block(11);
+        funding_id
+    }
+
+    #[test]
+    fn budget_line_should_cap_the_requests() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = funding_with_budget();
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 400, 0, 100, vec![(4, 400)], None), "The remain money of the budget line is not enough");
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 2, 100, vec![(4, 100)], None), "The budget line does not exist");
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 300, 0, 100, vec![(4, 300)], None));
+        })
+    }
+
+    #[test]
+    fn reallocation_should_move_the_share() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            let funding_id = funding_with_budget();
+            assert_err!(Requests::propose_reallocation(Origin::signed(1), funding_id, 0, 0, Permill::from_percent(10), 100), "The budget lines must be different");
+            assert_ok!(Requests::propose_reallocation(Origin::signed(1), funding_id, 1, 0, Permill::from_percent(10), 100));
+            assert_ok!(Requests::support_request(Origin::signed(2), last_request()));
+            assert_eq!(Requests::get_request_status(last_request()), 1);
+
+            assert_eq!(Fundings::get_budget_line_cap(funding_id, 0), Ok(Some(400)));
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 400, 0, 100, vec![(4, 400)], None));
+        })
+    }
+
+    #[test]
+    fn velocity_should_limit_the_approvals() {
+        with_externalities(&mut build_ext(), || {
+            <ExecutionDelay<FundingTest>>::put(0);
+            // approvals 5 blocks apart at least and 20% of the money released in 100 blocks at most
+            let velocity = Some((5, 100, Permill::from_percent(20)));
+            assert_err!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], Some((5, 0, Permill::from_percent(20))), vec![]), "The velocity window has to be greater than zero");
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], velocity, vec![]));
+            let funding_id = crate::funding_factory::tests::last_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 1000));
+            run_to_block(11);
+
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 300, 0, 100, vec![(4, 300)], None), "The funding has released too much money in the window");
+            let request_id = spending_request(funding_id, 150);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 1);
+
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 10, 0, 100, vec![(4, 10)], None), "The funding has approved a request too recently");
+            run_to_block(16);
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 100)], None), "The funding has released too much money in the window");
+            spending_request(funding_id, 50);
+
+            // the release leaves the window
+            run_to_block(111);
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 200, 0, 200, vec![(4, 200)], None));
+        })
+    }
+
+    #[test]
+    fn required_ratio_should_grow_with_the_request() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = funding_of_three();
+            // 5% of the remain money needs a majority
+            let request_id = spending_request(funding_id, 50);
+            assert_eq!(Requests::request_details(request_id).map(|details| details.required_ratio), Some(Permill::from_percent(50)));
+            // 60% of the remain money needs 90%
+            let request_id = spending_request(funding_id, 600);
+            assert_eq!(Requests::request_details(request_id).map(|details| details.required_ratio), Some(Permill::from_percent(90)));
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 0);
+        })
+    }
+
+    #[test]
+    fn dissolve_should_require_the_top_ratio() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = funding_of_three();
+            assert_ok!(Requests::propose_dissolve(Origin::signed(2), funding_id, 100));
+            let request_id = last_request();
+            assert_eq!(Requests::request_details(request_id).map(|details| details.required_ratio), Some(Permill::from_percent(90)));
+
+            // two thirds of the investors are not enough
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 0);
+            assert_ok!(Requests::support_request(Origin::signed(5), request_id));
+            assert_eq!(Requests::get_request_status(request_id), 4);
+        })
+    }
+
+    #[test]
+    fn error_should_encode_as_its_code() {
+        let encoded = RequestError::PayeeNotExist.encode();
+        assert_eq!(encoded, 261u16.encode());
+        assert_eq!(RequestError::decode(&mut &encoded[..]), Some(RequestError::PayeeNotExist));
+        assert_eq!(RequestError::decode(&mut &100u16.encode()[..]), None);
+        assert_eq!(RequestError::from_code(262).map(RequestError::code), Some(262));
+        assert_eq!(<&'static str>::from(RequestError::PayeeNotExist), RequestError::PayeeNotExist.as_str());
+        assert_eq!(RequestError::from_message("The beneficiary account does not exist"), Some(RequestError::PayeeNotExist));
+        assert_eq!(RequestError::from_message("bad origin: expected to be a root origin"), None);
+    }
+
+    #[test]
+    fn funding_details_should_leave_out_the_used_money() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            <ExecutionDelay<FundingTest>>::put(0);
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+            assert_ok!(Requests::support_request(Origin::signed(3), request_id));
+
+            let details = Requests::funding_details(funding_id).unwrap();
+            assert_eq!((details.raised, details.investor_count, details.remaining), (1000, 2, 900));
+            let requests = Requests::requests_of_funding(funding_id);
+            assert_eq!(requests.len(), 1);
+            assert_eq!((requests[0].request_id, requests[0].status, requests[0].supported), (request_id, 1, 2));
+            assert_eq!(Fundings::fundings_of_investor(2), vec![funding_id]);
+            assert!(Requests::funding_details(H256::repeat_byte(1)).is_none());
+        })
+    }
+
+    #[test]
+    fn set_parameters_should_limit_the_new_requests() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            assert_noop!(Requests::set_parameters(Origin::signed(1), Some(50), None), "bad origin: expected to be a root origin");
+            assert_err!(Requests::set_parameters(Origin::ROOT, Some(0), None), "The period limit has to be greater than zero");
+
+            assert_ok!(Requests::set_parameters(Origin::ROOT, Some(50), Some(1)));
+            assert_eq!(Requests::request_period_limit(), 50);
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 100)], None), "The expiry has be lower than the limit block number");
+            assert_ok!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 50, vec![(4, 100)], None));
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"seeds".to_vec(), 100, 0, 50, vec![(4, 100)], None), "Maximum number of requests is reached for the target block, try another block");
+        })
+    }
+
+    #[test]
+    fn frozen_funding_should_not_take_requests() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            let request_id = spending_request(funding_id, 100);
+            assert_ok!(Fundings::freeze_funding(Origin::ROOT, funding_id, true, H256::zero()));
+
+            assert_err!(Requests::create_request(Origin::signed(1), funding_id, b"tools".to_vec(), 100, 0, 100, vec![(4, 100)], None), "The funding is frozen");
+            assert_err!(Requests::support_request(Origin::signed(2), request_id), "The funding is frozen");
+            assert_ok!(Fundings::freeze_funding(Origin::ROOT, funding_id, false, H256::zero()));
+            assert_ok!(Requests::support_request(Origin::signed(2), request_id));
+        })
+    }
+
+    #[test]
+    fn force_refund_should_return_the_raising_investments() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Fundings::create_funding(Origin::signed(1), b"garden".to_vec(), 1000, 0, 10, 0, 0, vec![], None, vec![]));
+            let funding_id = last_funding();
+            assert_ok!(Fundings::invest(Origin::signed(2), funding_id, 300));
+            assert_eq!(Balances::reserved_balance(2), 300);
+
+            assert_noop!(Requests::force_refund(Origin::signed(1), funding_id, H256::zero()), "bad origin: expected to be a root origin");
+            assert_err!(Requests::force_refund(Origin::ROOT, H256::repeat_byte(1), H256::zero()), "The funding does not exist");
+            assert_ok!(Requests::force_refund(Origin::ROOT, funding_id, H256::zero()));
+            assert_eq!(Fundings::is_funding_success(funding_id), 3);
+            assert_eq!(Balances::reserved_balance(2), 0);
+            assert_eq!(Balances::free_balance(2), 100000);
+
+            // the dissolved funding is not finalized at its expiry and can't be refunded again
+            run_to_block(11);
+            assert_eq!(Fundings::is_funding_success(funding_id), 3);
+            assert_err!(Requests::force_refund(Origin::ROOT, funding_id, H256::zero()), "The funding is not running");
+        })
+    }
+
+    #[test]
+    fn force_refund_should_dissolve_the_succeeded_funding() {
+        with_externalities(&mut build_ext(), || {
+            let funding_id = succeeded_funding();
+            assert_eq!(Balances::reserved_balance(1), 1000);
+
+            assert_ok!(Requests::force_refund(Origin::ROOT, funding_id, H256::zero()));
+            assert_eq!(Fundings::is_funding_success(funding_id), 3);
+            assert_eq!(Balances::reserved_balance(1), 0);
+            assert_eq!(Balances::free_balance(1), 100000);
+            assert_eq!(Balances::free_balance(2), 100000);
+            assert_eq!(Balances::free_balance(3), 100000);
+        })
+    }
+}
@@ -1,6 +1,7 @@
 use primitives::{ed25519, sr25519, Pair};
 use substrate_crowdfunding_runtime::{
-    AccountId, BalancesConfig, ConsensusConfig, GenesisConfig, IndicesConfig, SudoConfig,
+    AccountId, Balance, BalancesConfig, BlockNumber, ConsensusConfig, DisputeConfig,
+    FundingFactoryConfig, GenesisConfig, IndicesConfig, Permill, RequestConfig, SudoConfig,
     TimestampConfig,
 };
 use substrate_service;
@@ -48,6 +49,7 @@ impl Alternative {
                         vec![authority_key("Alice")],
                         vec![account_key("Alice")],
                         account_key("Alice"),
+                        DemoFundings::default(),
                     )
                 },
                 vec![],
@@ -71,6 +73,7 @@ impl Alternative {
                             account_key("Ferdie"),
                         ],
                         account_key("Alice"),
+                        demo_fundings(),
                     )
                 },
                 vec![],
@@ -91,10 +94,41 @@ impl Alternative {
     }
 }
 
+/// The fundings, investments and requests that a chain boots with.
+#[derive(Default)]
+struct DemoFundings {
+    fundings: Vec<(AccountId, Vec<u8>, Balance, Balance, BlockNumber)>,
+    investments: Vec<(u64, AccountId, Balance)>,
+    requests: Vec<(u64, Vec<u8>, Balance, BlockNumber, Vec<(AccountId, Balance)>)>,
+}
+
+// A funding finalized with success and a request to vote on, and a funding still raising
+fn demo_fundings() -> DemoFundings {
+    DemoFundings {
+        fundings: vec![
+            (account_key("Bob"), b"Community garden".to_vec(), 1 << 50, 1 << 48, 0),
+            (account_key("Charlie"), b"Open source editor".to_vec(), 1 << 52, 0, 100800),
+        ],
+        investments: vec![
+            (0, account_key("Dave"), 1 << 49),
+            (0, account_key("Eve"), 1 << 49),
+            (1, account_key("Ferdie"), 1 << 50),
+        ],
+        requests: vec![(
+            0,
+            b"Seeds and tools".to_vec(),
+            1 << 48,
+            14400,
+            vec![(account_key("Bob"), 1 << 48)],
+        )],
+    }
+}
+
 fn testnet_genesis(
     initial_authorities: Vec<AuthorityId>,
     endowed_accounts: Vec<AccountId>,
     root_key: AccountId,
+    demo: DemoFundings,
 ) -> GenesisConfig {
     GenesisConfig {
 		consensus: Some(ConsensusConfig {
@@ -118,7 +152,33 @@ fn testnet_genesis(
 			vesting: vec![],
 		}),
 		sudo: Some(SudoConfig {
-			key: root_key,
+			key: root_key.clone(),
+		}),
+		funding_factory: Some(FundingFactoryConfig {
+			funding_period_limit: 777600,
+			max_fundings_per_block: 3,
+			min_target_money: 0,
+			max_name_length: 128,
+			listing_fee: 0,
+			success_fee: Permill::from_percent(2),
+			fundings: demo.fundings,
+			investments: demo.investments,
+		}),
+		request: Some(RequestConfig {
+			request_period_limit: 60480,
+			max_requests_per_block: 3,
+			execution_delay: 8640,
+			request_bond_ratio: Permill::from_percent(5),
+			cancellation_fee: Permill::from_percent(20),
+			challenge_bond: 1 << 40,
+			proof_period: 60480,
+			requests: demo.requests,
+		}),
+		dispute: Some(DisputeConfig {
+			arbitrators: vec![root_key],
+			dispute_bond: 1 << 40,
+			min_dispute_stake: 1 << 40,
+			dispute_period: 60480,
 		}),
 	}
 }
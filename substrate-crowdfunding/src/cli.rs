@@ -1,13 +1,54 @@
 use crate::chain_spec;
+use crate::export::{self, ExportFundingsCmd};
+use crate::pubsub;
+use crate::rpc;
 use crate::service;
+use consensus::SlotDuration;
 use futures::{future, sync::oneshot, Future};
 use log::info;
 use std::cell::RefCell;
+use std::net::SocketAddr;
 use std::ops::Deref;
+use std::sync::Arc;
+use structopt::StructOpt;
 pub use substrate_cli::{error, IntoExit, VersionInfo};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
-use substrate_service::{Roles as ServiceRoles, ServiceFactory};
-use tokio::runtime::Runtime;
+use substrate_cli::{impl_augment_clap, informant, parse_and_execute, GetLogFilter};
+use substrate_service::{FullClient, Roles as ServiceRoles, ServiceFactory};
+use tokio::runtime::{Runtime, TaskExecutor};
+
+/// The custom parameters of the run command.
+#[derive(Debug, StructOpt, Clone, Default)]
+pub struct RunParams {
+    /// Specify the crowdfunding JSON-RPC server TCP port, 9955 by default
+    #[structopt(long = "crowdfunding-rpc-port", value_name = "PORT")]
+    pub crowdfunding_rpc_port: Option<u16>,
+
+    /// Specify the crowdfunding websocket server TCP port for the subscriptions, 9956 by default
+    #[structopt(long = "crowdfunding-ws-port", value_name = "PORT")]
+    pub crowdfunding_ws_port: Option<u16>,
+
+    /// Listen to all the interfaces for the crowdfunding JSON-RPC and websocket servers
+    #[structopt(long = "crowdfunding-rpc-external")]
+    pub crowdfunding_rpc_external: bool,
+}
+
+impl_augment_clap!(RunParams);
+
+/// The custom subcommands.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CustomCommand {
+    /// Export the fundings with their investments and requests at a block as CSV or JSON
+    #[structopt(name = "export-fundings")]
+    ExportFundings(ExportFundingsCmd),
+}
+
+impl GetLogFilter for CustomCommand {
+    fn get_log_filter(&self) -> Option<String> {
+        match self {
+            CustomCommand::ExportFundings(cmd) => cmd.shared_params.log.clone(),
+        }
+    }
+}
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
@@ -16,13 +57,13 @@ where
     T: Into<std::ffi::OsString> + Clone,
     E: IntoExit,
 {
-    parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+    let custom = parse_and_execute::<service::Factory, CustomCommand, RunParams, _, _, _, _, _>(
         load_spec,
         &version,
         "substrate-node",
         args,
         exit,
-        |exit, _custom_args, config| {
+        |exit, run_params, config| {
             info!("{}", version.name);
             info!("  version {}", config.full_version());
             info!("  by {}, 2017, 2018", version.author);
@@ -38,17 +79,22 @@ where
                         .map_err(|e| format!("{:?}", e))?,
                     exit,
                 ),
-                _ => run_until_exit(
-                    runtime,
-                    service::Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
-                    exit,
-                ),
+                _ => {
+                    let service = service::Factory::new_full(config, executor.clone())
+                        .map_err(|e| format!("{:?}", e))?;
+                    // the servers stop when they are dropped at the exit
+                    let _rpc_servers = start_rpc(&run_params, service.client(), executor)?;
+                    run_until_exit(runtime, service, exit)
+                }
             }
             .map_err(|e| format!("{:?}", e))
         },
-    )
-    .map_err(Into::into)
-    .map(|_| ())
+    )?;
+
+    match custom {
+        Some(CustomCommand::ExportFundings(cmd)) => export::run(cmd, load_spec, &version),
+        None => Ok(()),
+    }
 }
 
 fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
@@ -58,6 +104,33 @@ fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
     })
 }
 
+fn start_rpc(
+    params: &RunParams,
+    client: Arc<FullClient<service::Factory>>,
+    executor: TaskExecutor,
+) -> Result<(jsonrpc_http_server::Server, jsonrpc_ws_server::Server), String> {
+    let interface = if params.crowdfunding_rpc_external {
+        [0, 0, 0, 0]
+    } else {
+        [127, 0, 0, 1]
+    };
+    let slot_duration = SlotDuration::get_or_compute(&*client)
+        .map_err(|e| format!("{:?}", e))?
+        .get();
+
+    let http_addr = SocketAddr::from((interface, params.crowdfunding_rpc_port.unwrap_or(rpc::DEFAULT_PORT)));
+    let http = rpc::start_http(&http_addr, client.clone(), slot_duration)
+        .map_err(|e| format!("Unable to start the crowdfunding RPC on {}: {:?}", http_addr, e))?;
+    info!("Crowdfunding RPC listening on {}", http_addr);
+
+    let ws_addr = SocketAddr::from((interface, params.crowdfunding_ws_port.unwrap_or(pubsub::DEFAULT_PORT)));
+    let ws = pubsub::start_ws(&ws_addr, client, slot_duration, executor)
+        .map_err(|e| format!("Unable to start the crowdfunding websocket on {}: {:?}", ws_addr, e))?;
+    info!("Crowdfunding websocket listening on {}", ws_addr);
+
+    Ok((http, ws))
+}
+
 fn run_until_exit<T, C, E>(mut runtime: Runtime, service: T, e: E) -> error::Result<()>
 where
     T: Deref<Target = substrate_service::Service<C>>,
@@ -0,0 +1,412 @@
+//! The `export-fundings` subcommand, dumping the fundings with their investments and requests at a
+//! block from the node database, for the reconciliations that can not run a node and an RPC client.
+//!
+//! The node database is never written: it must exist, and the export opens a snapshot of it that
+//! is removed afterwards. The table files never change once written, so the snapshot links them
+//! and copies only the rest. The export refuses to run while a node holds the database lock, as
+//! the files would change under the snapshot.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use app_dirs::{AppDataType, AppInfo};
+use primitives::{crypto::Ss58Codec, H256};
+use serde_derive::Serialize;
+use sr_primitives::{
+    generic::BlockId,
+    traits::{Header as HeaderT, ProvideRuntimeApi},
+};
+use structopt::StructOpt;
+use substrate_cli::{error, SharedParams, VersionInfo};
+use substrate_crowdfunding_runtime::{BlockNumber, CrowdfundingApi};
+use substrate_service::{FactoryFullConfiguration, FullClient};
+
+use crate::chain_spec::ChainSpec;
+use crate::rpc::{funding_status, request_status};
+use crate::service;
+
+/// The format of the export.
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// One row per funding, investment and request.
+    Csv,
+    /// The fundings with their investments and requests nested.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format {}, use csv or json", s)),
+        }
+    }
+}
+
+/// The `export-fundings` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportFundingsCmd {
+    /// Specify the number or the 0x-prefixed hash of the block to export at, the best block by default
+    #[structopt(long = "at", value_name = "BLOCK")]
+    pub at: Option<String>,
+
+    /// Specify the format of the export: csv or json
+    #[structopt(long = "format", value_name = "FORMAT", default_value = "csv")]
+    pub format: Format,
+
+    /// Specify the output file, the standard output by default
+    #[structopt(long = "output", value_name = "PATH", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    #[allow(missing_docs)]
+    #[structopt(flatten)]
+    pub shared_params: SharedParams,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    block_number: BlockNumber,
+    block_hash: H256,
+    fundings: Vec<ExportedFunding>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedFunding {
+    id: H256,
+    manager: String,
+    project_name: String,
+    target_money: String,
+    raised: String,
+    remaining: String,
+    investor_count: u64,
+    status: &'static str,
+    expiry: BlockNumber,
+    investments: Vec<ExportedInvestment>,
+    requests: Vec<ExportedRequest>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedInvestment {
+    investor: String,
+    amount: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedRequest {
+    id: H256,
+    owner: Option<String>,
+    purpose: String,
+    cost: String,
+    status: &'static str,
+    supported: u64,
+    opposed: u64,
+    expiry: BlockNumber,
+}
+
+/// Run the command on the database of the chain.
+pub fn run<S>(cmd: ExportFundingsCmd, spec_factory: S, version: &VersionInfo) -> error::Result<()>
+where
+    S: FnOnce(&str) -> Result<Option<ChainSpec>, String>,
+{
+    let (_snapshot, client) = open_client(&cmd.shared_params, spec_factory, version)?;
+    let export = read_export(&client, cmd.at.as_ref())?;
+
+    let mut output: Box<dyn Write> = match &cmd.output {
+        Some(path) => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(io::BufWriter::new(io::stdout())),
+    };
+    match cmd.format {
+        Format::Csv => write_csv(&export, &mut output)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut output, &export).map_err(|e| format!("{}", e))?;
+            writeln!(output)?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+// A snapshot of the node database next to it, removed when dropped
+struct Snapshot(PathBuf);
+
+impl Snapshot {
+    fn of(database_path: &Path) -> error::Result<Self> {
+        if !database_path.join("CURRENT").is_file() {
+            return Err(format!("No database at {}", database_path.display()).into());
+        }
+        if is_locked(&database_path.join("LOCK"))? {
+            return Err(format!(
+                "The database at {} is in use, stop the node before exporting",
+                database_path.display()
+            )
+            .into());
+        }
+        // a sibling of the database stays on the same file system, so the table files can be linked
+        let path = database_path.with_file_name(format!("db-export-{}", std::process::id()));
+        let snapshot = Snapshot(path);
+        fs::create_dir_all(&snapshot.0)?;
+        for entry in fs::read_dir(database_path)? {
+            let entry = entry?;
+            // the lock belongs to the node, the snapshot takes its own
+            if !entry.file_type()?.is_file() || entry.file_name() == "LOCK" {
+                continue;
+            }
+            let target = snapshot.0.join(entry.file_name());
+            let is_table = entry
+                .path()
+                .extension()
+                .map_or(false, |extension| extension == "sst");
+            if !is_table || fs::hard_link(entry.path(), &target).is_err() {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+// Judge if a process holds the lock of the database, as the node does while it runs
+#[cfg(unix)]
+fn is_locked(lock_path: &Path) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = match fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(lock_path)
+    {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    // ask for the lock that would block a write lock on the whole file, without taking it
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(lock.l_type != libc::F_UNLCK as libc::c_short)
+}
+
+// Judge if a process holds the lock of the database, the node opens the lock file without sharing it
+#[cfg(not(unix))]
+fn is_locked(lock_path: &Path) -> io::Result<bool> {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    match fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(lock_path)
+    {
+        Ok(_) => Ok(false),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(ref e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn open_client<S>(
+    params: &SharedParams,
+    spec_factory: S,
+    version: &VersionInfo,
+) -> error::Result<(Snapshot, std::sync::Arc<FullClient<service::Factory>>)>
+where
+    S: FnOnce(&str) -> Result<Option<ChainSpec>, String>,
+{
+    let chain_key = if params.dev {
+        "dev".into()
+    } else {
+        params.chain.clone().unwrap_or_default()
+    };
+    let spec = match spec_factory(&chain_key)? {
+        Some(spec) => spec,
+        None => ChainSpec::from_json_file(PathBuf::from(chain_key))?,
+    };
+
+    let base_path = match &params.base_path {
+        Some(base_path) => base_path.clone(),
+        None => app_dirs::get_app_root(
+            AppDataType::UserData,
+            &AppInfo {
+                name: version.executable_name,
+                author: version.author,
+            },
+        )
+        .map_err(|e| format!("Unable to find the base path: {:?}", e))?,
+    };
+    let mut config = FactoryFullConfiguration::<service::Factory>::default_with_spec(spec);
+    let database_path = base_path
+        .join("chains")
+        .join(config.chain_spec.id())
+        .join("db");
+    // the client opens its database read-write and creates the genesis of an empty one
+    let snapshot = Snapshot::of(&database_path)?;
+    config.database_path = snapshot.0.to_string_lossy().into();
+
+    let client = substrate_service::new_client::<service::Factory>(&config)?;
+    Ok((snapshot, client))
+}
+
+fn read_export(
+    client: &FullClient<service::Factory>,
+    at: Option<&String>,
+) -> error::Result<Export> {
+    let at = match at {
+        Some(at) if at.starts_with("0x") => BlockId::hash(
+            at[2..]
+                .parse::<H256>()
+                .map_err(|_| format!("Invalid block hash {}", at))?,
+        ),
+        Some(at) => BlockId::number(
+            at.parse::<BlockNumber>()
+                .map_err(|_| format!("Invalid block number {}", at))?,
+        ),
+        None => BlockId::hash(client.info()?.chain.best_hash),
+    };
+    let header = client
+        .header(&at)?
+        .ok_or_else(|| format!("Unknown block {:?}", at))?;
+    let at = BlockId::hash(header.hash());
+
+    let api = client.runtime_api();
+    let mut fundings = Vec::new();
+    for details in api.list_fundings(&at, 0, u64::max_value(), None)? {
+        let investments = api
+            .investments_of_funding(&at, details.funding_id)?
+            .into_iter()
+            .map(|(investor, amount)| ExportedInvestment {
+                investor: investor.to_ss58check(),
+                amount: amount.to_string(),
+            })
+            .collect();
+        let requests = api
+            .requests_of_funding(&at, details.funding_id)?
+            .into_iter()
+            .map(|request| ExportedRequest {
+                id: request.request_id,
+                owner: request.owner.map(|owner| owner.to_ss58check()),
+                purpose: String::from_utf8_lossy(&request.purpose).into_owned(),
+                cost: request.cost.to_string(),
+                status: request_status(request.status),
+                supported: request.supported,
+                opposed: request.opposed,
+                expiry: request.expiry,
+            })
+            .collect();
+        fundings.push(ExportedFunding {
+            id: details.funding_id,
+            manager: details.manager.to_ss58check(),
+            project_name: String::from_utf8_lossy(&details.project_name).into_owned(),
+            target_money: details.target_money.to_string(),
+            raised: details.raised.to_string(),
+            remaining: details.remaining.to_string(),
+            investor_count: details.investor_count,
+            status: funding_status(details.status),
+            expiry: details.expiry,
+            investments,
+            requests,
+        });
+    }
+
+    Ok(Export {
+        block_number: *header.number(),
+        block_hash: header.hash(),
+        fundings,
+    })
+}
+
+// One row per funding, investment and request, the `record` column tells them apart
+fn write_csv<W: Write>(export: &Export, output: &mut W) -> io::Result<()> {
+    writeln!(
+        output,
+        "record,block_number,funding_id,request_id,account,name,amount,raised,status,supported,opposed,expiry"
+    )?;
+    for funding in &export.fundings {
+        let funding_id = format!("{:?}", funding.id);
+        write_row(
+            output,
+            &[
+                "funding",
+                &export.block_number.to_string(),
+                &funding_id,
+                "",
+                &funding.manager,
+                &funding.project_name,
+                &funding.target_money,
+                &funding.raised,
+                funding.status,
+                "",
+                "",
+                &funding.expiry.to_string(),
+            ],
+        )?;
+        for investment in &funding.investments {
+            write_row(
+                output,
+                &[
+                    "investment",
+                    &export.block_number.to_string(),
+                    &funding_id,
+                    "",
+                    &investment.investor,
+                    "",
+                    &investment.amount,
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                ],
+            )?;
+        }
+        for request in &funding.requests {
+            write_row(
+                output,
+                &[
+                    "request",
+                    &export.block_number.to_string(),
+                    &funding_id,
+                    &format!("{:?}", request.id),
+                    request.owner.as_ref().map_or("", String::as_str),
+                    &request.purpose,
+                    &request.cost,
+                    "",
+                    request.status,
+                    &request.supported.to_string(),
+                    &request.opposed.to_string(),
+                    &request.expiry.to_string(),
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_row<W: Write>(output: &mut W, fields: &[&str]) -> io::Result<()> {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    writeln!(output, "{}", fields.join(","))
+}
@@ -4,7 +4,11 @@
 #![warn(unused_extern_crates)]
 
 mod chain_spec;
+mod module_error;
 mod cli;
+mod export;
+mod pubsub;
+mod rpc;
 mod service;
 
 pub use substrate_cli::{error, IntoExit, VersionInfo};
@@ -0,0 +1,87 @@
+//! Decoding of the failed extrinsics of the crowdfunding modules into typed errors.
+
+use parity_codec::Decode;
+use primitives::{storage::StorageKey, twox_128, Blake2Hasher};
+use substrate_client as client;
+use substrate_crowdfunding_runtime::{
+	dispute, funding_factory, matching, opaque::{Block, BlockId}, request, Event, EventRecords, Phase, SystemEvent,
+};
+
+/// The error that a call of a crowdfunding module failed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleError {
+	/// Error of the funding module, codes 1xx.
+	FundingFactory(funding_factory::FundingError),
+	/// Error of the request module, codes 2xx.
+	Request(request::RequestError),
+	/// Error of the dispute module, codes 3xx.
+	Dispute(dispute::DisputeError),
+	/// Error of the matching module, codes 4xx.
+	Matching(matching::MatchingError),
+}
+
+impl ModuleError {
+	/// Get the error of the stable code.
+	pub fn from_code(code: u16) -> Option<Self> {
+		funding_factory::FundingError::from_code(code).map(ModuleError::FundingFactory)
+			.or_else(|| request::RequestError::from_code(code).map(ModuleError::Request))
+			.or_else(|| dispute::DisputeError::from_code(code).map(ModuleError::Dispute))
+			.or_else(|| matching::MatchingError::from_code(code).map(ModuleError::Matching))
+	}
+
+	/// The stable code that clients use to localize the message.
+	pub fn code(&self) -> u16 {
+		match self {
+			ModuleError::FundingFactory(e) => e.code(),
+			ModuleError::Request(e) => e.code(),
+			ModuleError::Dispute(e) => e.code(),
+			ModuleError::Matching(e) => e.code(),
+		}
+	}
+
+	/// The English message of the error.
+	pub fn message(&self) -> &'static str {
+		match self {
+			ModuleError::FundingFactory(e) => e.as_str(),
+			ModuleError::Request(e) => e.as_str(),
+			ModuleError::Dispute(e) => e.as_str(),
+			ModuleError::Matching(e) => e.as_str(),
+		}
+	}
+}
+
+/// Read the events of the block from the storage of the system module.
+pub fn events_at<B, E, RA>(client: &client::Client<B, E, Block, RA>, at: &BlockId) -> client::error::Result<EventRecords> where
+	B: client::backend::Backend<Block, Blake2Hasher>,
+	E: client::CallExecutor<Block, Blake2Hasher>,
+{
+	let key = StorageKey(twox_128(b"System Events").to_vec());
+	let events = client.storage(at, &key)?
+		.and_then(|data| Decode::decode(&mut &data.0[..]))
+		.unwrap_or_default();
+	Ok(events)
+}
+
+/// Decode the error that the extrinsic at the index failed with from the events of its block.
+///
+/// Only the extrinsic that the system module reports as failed has an error, its call records the error
+/// that it failed with, which may be an error of a module that it calls.
+pub fn decode_failed_extrinsic(events: &EventRecords, index: u32) -> Option<ModuleError> {
+	let records = events.iter().filter(|record| record.phase == Phase::ApplyExtrinsic(index));
+	let failed = records.clone().any(|record| match record.event {
+		Event::system(SystemEvent::ExtrinsicFailed) => true,
+		_ => false,
+	});
+	if !failed {
+		return None;
+	}
+	records
+		.filter_map(|record| match record.event {
+			Event::funding_factory(funding_factory::RawEvent::CallFailed(error)) => Some(ModuleError::FundingFactory(error)),
+			Event::request(request::RawEvent::CallFailed(error)) => Some(ModuleError::Request(error)),
+			Event::dispute(dispute::RawEvent::CallFailed(error)) => Some(ModuleError::Dispute(error)),
+			Event::matching(matching::RawEvent::CallFailed(error)) => Some(ModuleError::Matching(error)),
+			_ => None,
+		})
+		.next()
+}
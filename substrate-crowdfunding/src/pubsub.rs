@@ -0,0 +1,398 @@
+//! The `crowdfunding_subscribeFunding` subscription, pushing the progress of a funding whenever
+//! the events of an imported best block touch it.
+//!
+//! The blocks are walked along the new best route, so a block imported on a fork is handled
+//! once the fork becomes the best chain. An update is first sent with `confirmations: 0`. Once its
+//! block is finalized, or buried `CONFIRMATIONS` blocks deep in the best chain, the update is sent
+//! again with the number of blocks built on it and whether the client has really finalized it.
+//! The node runs Aura without a finality gadget, so the depth is only a heuristic and a deep
+//! enough reorg can still drop a confirmed block. An update whose block leaves the best chain is
+//! never confirmed, the block replacing it sends its own update.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures::{stream, sync::oneshot, Future, Sink, Stream};
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, PubSubMetadata, Session, SubscriptionId};
+use log::warn;
+use parking_lot::Mutex;
+use primitives::{Blake2Hasher, H256};
+use serde_derive::Serialize;
+use sr_primitives::{
+    generic::BlockId,
+    traits::{Header as HeaderT, ProvideRuntimeApi},
+};
+use substrate_client::{backend::Backend, blockchain::tree_route, CallExecutor, Client};
+use substrate_crowdfunding_runtime::{
+    funding_factory, matching, opaque::Block, request, BlockNumber, CrowdfundingApi, Event,
+};
+use tokio::runtime::TaskExecutor;
+
+use crate::module_error;
+use crate::rpc::funding_status;
+
+/// The default port of the crowdfunding websocket server.
+pub const DEFAULT_PORT: u16 = 9956;
+
+/// The depth in the best chain after which an update is sent again, a heuristic and not finality.
+pub const CONFIRMATIONS: BlockNumber = 6;
+
+/// The progress of a funding after a block.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingUpdate {
+    pub funding_id: H256,
+    pub block_hash: H256,
+    pub block_number: BlockNumber,
+    /// The blocks built on the block in the best chain when the update was sent.
+    pub confirmations: BlockNumber,
+    /// Whether the client has finalized the block.
+    pub finalized: bool,
+    pub raised: String,
+    pub remaining: String,
+    pub investor_count: u64,
+    pub status: &'static str,
+}
+
+/// The metadata of a websocket session.
+#[derive(Clone, Default)]
+pub struct Metadata {
+    session: Option<Arc<Session>>,
+}
+
+impl Metadata {
+    /// Create the metadata of the session sending the notifications to the transport.
+    pub fn new(transport: futures::sync::mpsc::Sender<String>) -> Self {
+        Metadata {
+            session: Some(Arc::new(Session::new(transport))),
+        }
+    }
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+impl PubSubMetadata for Metadata {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.session.clone()
+    }
+}
+
+/// The crowdfunding subscriptions.
+#[rpc]
+pub trait CrowdfundingPubSub {
+    /// The metadata of the session.
+    type Metadata;
+
+    /// Subscribe to the progress of the funding.
+    #[pubsub(
+        subscription = "crowdfunding_funding",
+        subscribe,
+        name = "crowdfunding_subscribeFunding"
+    )]
+    fn subscribe_funding(
+        &self,
+        metadata: Self::Metadata,
+        subscriber: Subscriber<FundingUpdate>,
+        funding_id: H256,
+    );
+
+    /// Unsubscribe from the progress of the funding.
+    #[pubsub(
+        subscription = "crowdfunding_funding",
+        unsubscribe,
+        name = "crowdfunding_unsubscribeFunding"
+    )]
+    fn unsubscribe_funding(
+        &self,
+        metadata: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool>;
+}
+
+/// The implementation of the crowdfunding subscriptions over a client.
+pub struct CrowdfundingSubscriptions<B, E, RA> {
+    client: Arc<Client<B, E, Block, RA>>,
+    executor: TaskExecutor,
+    next_id: AtomicUsize,
+    active: Arc<Mutex<HashMap<u64, oneshot::Sender<()>>>>,
+}
+
+impl<B, E, RA> CrowdfundingSubscriptions<B, E, RA> {
+    /// Create the subscriptions, the notifications are sent from tasks spawned on the executor.
+    pub fn new(client: Arc<Client<B, E, Block, RA>>, executor: TaskExecutor) -> Self {
+        CrowdfundingSubscriptions {
+            client,
+            executor,
+            next_id: AtomicUsize::new(0),
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<B, E, RA> CrowdfundingPubSub for CrowdfundingSubscriptions<B, E, RA>
+where
+    B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+    E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+    RA: Send + Sync + 'static,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    type Metadata = Metadata;
+
+    fn subscribe_funding(
+        &self,
+        _metadata: Self::Metadata,
+        subscriber: Subscriber<FundingUpdate>,
+        funding_id: H256,
+    ) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u64;
+        let sink = match subscriber.assign_id(SubscriptionId::Number(id)) {
+            Ok(sink) => sink,
+            Err(_) => return,
+        };
+        let (cancel, cancelled) = oneshot::channel();
+        self.active.lock().insert(id, cancel);
+
+        let client = self.client.clone();
+        let mut pending = Vec::new();
+        let mut best = None;
+        let updates = self
+            .client
+            .import_notification_stream()
+            .filter(|notification| notification.is_new_best)
+            .map(move |notification| {
+                let number = *notification.header.number();
+                let route = enacted_route(&client, best, notification.hash, number);
+                best = Some(notification.hash);
+                let updates = route
+                    .into_iter()
+                    .flat_map(|(number, hash)| {
+                        updates_at(&client, funding_id, hash, number, &mut pending)
+                    })
+                    .collect::<Vec<_>>();
+                stream::iter_ok(updates.into_iter().map(Ok))
+            })
+            .flatten();
+
+        // The entry is removed when the client disconnects as well as when it unsubscribes
+        let active = self.active.clone();
+        let future = sink
+            .sink_map_err(|e| warn!("Error sending the funding update: {:?}", e))
+            .send_all(updates)
+            .map(|_| ())
+            .select(cancelled.map_err(|_| ()))
+            .then(move |_| {
+                active.lock().remove(&id);
+                Ok::<(), ()>(())
+            });
+        self.executor.spawn(future);
+    }
+
+    fn unsubscribe_funding(
+        &self,
+        _metadata: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        let removed = match id {
+            SubscriptionId::Number(id) => self.active.lock().remove(&id),
+            _ => None,
+        };
+        Ok(match removed {
+            Some(cancel) => {
+                let _ = cancel.send(());
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+// Get the blocks enacted from the common ancestor of the last best block up to the new best block
+fn enacted_route<B, E, RA>(
+    client: &Arc<Client<B, E, Block, RA>>,
+    last_best: Option<H256>,
+    hash: H256,
+    number: BlockNumber,
+) -> Vec<(BlockNumber, H256)>
+where
+    B: Backend<Block, Blake2Hasher>,
+    E: CallExecutor<Block, Blake2Hasher>,
+{
+    let last_best = match last_best {
+        Some(last_best) => last_best,
+        None => return vec![(number, hash)],
+    };
+    match tree_route(
+        client.backend().blockchain(),
+        BlockId::hash(last_best),
+        BlockId::hash(hash),
+    ) {
+        Ok(route) => route
+            .enacted()
+            .iter()
+            .map(|entry| (entry.number, entry.hash))
+            .collect(),
+        Err(e) => {
+            warn!(
+                "Error getting the route to the best block {}: {:?}",
+                hash, e
+            );
+            vec![(number, hash)]
+        }
+    }
+}
+
+// Confirm the updates sent before and send a new one if the block touches the funding
+fn updates_at<B, E, RA>(
+    client: &Arc<Client<B, E, Block, RA>>,
+    funding_id: H256,
+    hash: H256,
+    number: BlockNumber,
+    pending: &mut Vec<(BlockNumber, H256)>,
+) -> Vec<FundingUpdate>
+where
+    B: Backend<Block, Blake2Hasher>,
+    E: CallExecutor<Block, Blake2Hasher>,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    let mut updates = Vec::new();
+
+    let finalized_number = client
+        .info()
+        .map(|info| info.chain.finalized_number)
+        .unwrap_or_default();
+    pending.retain(|&(pending_number, pending_hash)| {
+        if pending_number > finalized_number && pending_number + CONFIRMATIONS > number {
+            return true;
+        }
+        // The block is confirmed only if it is still in the best chain
+        let canonical = client.block_hash(pending_number).ok().and_then(|hash| hash);
+        if canonical == Some(pending_hash) {
+            let confirmations = number - pending_number;
+            let finalized = pending_number <= finalized_number;
+            updates.extend(funding_update(
+                client,
+                funding_id,
+                pending_hash,
+                pending_number,
+                confirmations,
+                finalized,
+            ));
+        }
+        false
+    });
+
+    let at = BlockId::hash(hash);
+    let events = module_error::events_at(client, &at).unwrap_or_default();
+    if events
+        .iter()
+        .any(|record| touches(client, &at, &record.event, funding_id))
+    {
+        if let Some(update) = funding_update(
+            client,
+            funding_id,
+            hash,
+            number,
+            0,
+            number <= finalized_number,
+        ) {
+            pending.push((number, hash));
+            updates.push(update);
+        }
+    }
+    updates
+}
+
+// Judge if the event changes the totals or the status of the funding
+fn touches<B, E, RA>(
+    client: &Arc<Client<B, E, Block, RA>>,
+    at: &BlockId<Block>,
+    event: &Event,
+    funding_id: H256,
+) -> bool
+where
+    B: Backend<Block, Blake2Hasher>,
+    E: CallExecutor<Block, Blake2Hasher>,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    let request_id = match event {
+        Event::funding_factory(funding_factory::RawEvent::Invest(id, _, _))
+        | Event::funding_factory(funding_factory::RawEvent::FundingFinalized(id, _, _, _))
+        | Event::request(request::RawEvent::CreateRequest(_, id, _, _, _))
+        | Event::request(request::RawEvent::Refund(id, _, _))
+        | Event::request(request::RawEvent::FundingDissolved(id, _))
+        | Event::matching(matching::RawEvent::Matched(_, id, _, _))
+        | Event::matching(matching::RawEvent::MatchPaid(_, id, _)) => return *id == funding_id,
+        Event::request(request::RawEvent::Vote(_, request_id))
+        | Event::request(request::RawEvent::RequestFinalized(request_id, _, _, _, _))
+        | Event::request(request::RawEvent::Payout(request_id, _, _)) => *request_id,
+        _ => return false,
+    };
+    match client.runtime_api().request_details(at, request_id) {
+        Ok(Some(request)) => request.funding_id == funding_id,
+        _ => false,
+    }
+}
+
+fn funding_update<B, E, RA>(
+    client: &Arc<Client<B, E, Block, RA>>,
+    funding_id: H256,
+    hash: H256,
+    number: BlockNumber,
+    confirmations: BlockNumber,
+    finalized: bool,
+) -> Option<FundingUpdate>
+where
+    B: Backend<Block, Blake2Hasher>,
+    E: CallExecutor<Block, Blake2Hasher>,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    let details = client
+        .runtime_api()
+        .funding_details(&BlockId::hash(hash), funding_id)
+        .ok()??;
+    Some(FundingUpdate {
+        funding_id,
+        block_hash: hash,
+        block_number: number,
+        confirmations,
+        finalized,
+        raised: details.raised.to_string(),
+        remaining: details.remaining.to_string(),
+        investor_count: details.investor_count,
+        status: funding_status(details.status),
+    })
+}
+
+/// Start the crowdfunding websocket server, serving the subscriptions and the `crowdfunding_*`
+/// methods, it stops when the returned server is dropped.
+pub fn start_ws<B, E, RA>(
+    addr: &std::net::SocketAddr,
+    client: Arc<Client<B, E, Block, RA>>,
+    slot_duration: u64,
+    executor: TaskExecutor,
+) -> std::result::Result<jsonrpc_ws_server::Server, jsonrpc_ws_server::Error>
+where
+    B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+    E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+    RA: Send + Sync + 'static,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    use crate::rpc::{Crowdfunding, CrowdfundingRpc};
+
+    let mut io = jsonrpc_pubsub::PubSubHandler::<Metadata>::default();
+    io.extend_with(Crowdfunding::new(client.clone(), slot_duration).to_delegate());
+    io.extend_with(CrowdfundingSubscriptions::new(client, executor).to_delegate());
+    jsonrpc_ws_server::ServerBuilder::with_meta_extractor(
+        io,
+        |context: &jsonrpc_ws_server::RequestContext| Metadata::new(context.sender()),
+    )
+    .start(addr)
+}
@@ -0,0 +1,334 @@
+//! The `crowdfunding_*` JSON-RPC namespace, answering from the crowdfunding runtime API in
+//! human-readable JSON for the dashboards and scripts that do not speak SCALE.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::module_error::{self, ModuleError};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use primitives::{crypto::Ss58Codec, Blake2Hasher, H256};
+use serde_derive::Serialize;
+use sr_primitives::{generic::BlockId, traits::ProvideRuntimeApi};
+use substrate_client::{backend::Backend, CallExecutor, Client};
+use substrate_crowdfunding_runtime::{
+    opaque::Block, AccountId, Balance, BlockNumber, CrowdfundingApi, FundingDetails, RequestDetails,
+};
+
+/// The default port of the crowdfunding JSON-RPC server.
+pub const DEFAULT_PORT: u16 = 9955;
+
+/// The most fundings that `crowdfunding_listFundings` returns in a page.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// A funding as returned by the RPC.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Funding {
+    pub id: H256,
+    pub manager: String,
+    pub project_name: String,
+    pub target_money: String,
+    pub raised: String,
+    pub remaining: String,
+    pub investor_count: u64,
+    pub status: &'static str,
+    pub expiry: Expiry,
+}
+
+/// The investments of an account.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Portfolio {
+    pub investor: String,
+    pub total_invested: String,
+    pub investments: Vec<Investment>,
+}
+
+/// The money that an account has invested in a funding.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Investment {
+    pub funding: Funding,
+    pub invested: String,
+}
+
+/// The votes of a request against the ratio it requires.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestTally {
+    pub id: H256,
+    pub funding_id: H256,
+    pub purpose: String,
+    pub cost: String,
+    pub status: &'static str,
+    pub supported: u64,
+    pub opposed: u64,
+    pub investor_count: u64,
+    /// The required ratio of the investors in parts per million.
+    pub required_ratio: u64,
+    pub expiry: Expiry,
+}
+
+/// The typed error that an extrinsic failed with.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicError {
+    pub module: &'static str,
+    pub code: u16,
+    pub message: &'static str,
+}
+
+impl From<ModuleError> for ExtrinsicError {
+    fn from(error: ModuleError) -> Self {
+        let module = match error {
+            ModuleError::FundingFactory(_) => "fundingFactory",
+            ModuleError::Request(_) => "request",
+            ModuleError::Dispute(_) => "dispute",
+            ModuleError::Matching(_) => "matching",
+        };
+        ExtrinsicError {
+            module,
+            code: error.code(),
+            message: error.message(),
+        }
+    }
+}
+
+/// A deadline block with the estimated unix time when it is reached.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Expiry {
+    pub block: BlockNumber,
+    /// `None` if the block is in the past.
+    pub estimated_timestamp: Option<u64>,
+}
+
+/// The crowdfunding RPC methods.
+#[rpc]
+pub trait CrowdfundingRpc {
+    /// Get the funding.
+    #[rpc(name = "crowdfunding_getFunding")]
+    fn get_funding(&self, funding_id: H256) -> Result<Option<Funding>>;
+
+    /// List a page of at most `MAX_PAGE_SIZE` fundings, optionally only the ones with the status code.
+    #[rpc(name = "crowdfunding_listFundings")]
+    fn list_fundings(&self, offset: u64, limit: u64, status: Option<u64>) -> Result<Vec<Funding>>;
+
+    /// Get the fundings that the ss58 account has invested and the money it invested.
+    #[rpc(name = "crowdfunding_getInvestorPortfolio")]
+    fn get_investor_portfolio(&self, investor: String) -> Result<Portfolio>;
+
+    /// Get the votes of the request.
+    #[rpc(name = "crowdfunding_getRequestTally")]
+    fn get_request_tally(&self, request_id: H256) -> Result<Option<RequestTally>>;
+
+    /// Get the typed error that the extrinsic at the index of the block failed with.
+    #[rpc(name = "crowdfunding_getExtrinsicError")]
+    fn get_extrinsic_error(&self, block_hash: H256, index: u32) -> Result<Option<ExtrinsicError>>;
+}
+
+/// The implementation of the crowdfunding RPC over a client.
+pub struct Crowdfunding<B, E, RA> {
+    client: Arc<Client<B, E, Block, RA>>,
+    slot_duration: u64,
+}
+
+impl<B, E, RA> Crowdfunding<B, E, RA> {
+    /// Create the RPC, the slot duration in seconds is used to estimate the time of a block.
+    pub fn new(client: Arc<Client<B, E, Block, RA>>, slot_duration: u64) -> Self {
+        Crowdfunding { client, slot_duration }
+    }
+}
+
+impl<B, E, RA> Crowdfunding<B, E, RA>
+where
+    B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+    E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+    RA: Send + Sync + 'static,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    fn best(&self) -> Result<(BlockId<Block>, BlockNumber)> {
+        let info = self.client.info().map_err(client_error)?;
+        Ok((BlockId::hash(info.chain.best_hash), info.chain.best_number))
+    }
+
+    fn expiry(&self, block: BlockNumber, best_number: BlockNumber) -> Expiry {
+        let estimated_timestamp = if block > best_number {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            Some(now + (block - best_number) * self.slot_duration)
+        } else {
+            None
+        };
+        Expiry {
+            block,
+            estimated_timestamp,
+        }
+    }
+
+    fn funding(&self, details: FundingDetails, best_number: BlockNumber) -> Funding {
+        Funding {
+            id: details.funding_id,
+            manager: details.manager.to_ss58check(),
+            project_name: String::from_utf8_lossy(&details.project_name).into_owned(),
+            target_money: details.target_money.to_string(),
+            raised: details.raised.to_string(),
+            remaining: details.remaining.to_string(),
+            investor_count: details.investor_count,
+            status: funding_status(details.status),
+            expiry: self.expiry(details.expiry, best_number),
+        }
+    }
+
+    fn tally(&self, details: RequestDetails, investor_count: u64, best_number: BlockNumber) -> RequestTally {
+        RequestTally {
+            id: details.request_id,
+            funding_id: details.funding_id,
+            purpose: String::from_utf8_lossy(&details.purpose).into_owned(),
+            cost: details.cost.to_string(),
+            status: request_status(details.status),
+            supported: details.supported,
+            opposed: details.opposed,
+            investor_count,
+            required_ratio: details.required_ratio.deconstruct().into(),
+            expiry: self.expiry(details.expiry, best_number),
+        }
+    }
+}
+
+impl<B, E, RA> CrowdfundingRpc for Crowdfunding<B, E, RA>
+where
+    B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+    E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+    RA: Send + Sync + 'static,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    fn get_funding(&self, funding_id: H256) -> Result<Option<Funding>> {
+        let (at, best_number) = self.best()?;
+        let details = self
+            .client
+            .runtime_api()
+            .funding_details(&at, funding_id)
+            .map_err(client_error)?;
+        Ok(details.map(|details| self.funding(details, best_number)))
+    }
+
+    fn list_fundings(&self, offset: u64, limit: u64, status: Option<u64>) -> Result<Vec<Funding>> {
+        let (at, best_number) = self.best()?;
+        let fundings = self
+            .client
+            .runtime_api()
+            .list_fundings(&at, offset, limit.min(MAX_PAGE_SIZE), status)
+            .map_err(client_error)?;
+        Ok(fundings
+            .into_iter()
+            .map(|details| self.funding(details, best_number))
+            .collect())
+    }
+
+    fn get_investor_portfolio(&self, investor: String) -> Result<Portfolio> {
+        let account = AccountId::from_ss58check(&investor)
+            .map_err(|_| Error::invalid_params("Invalid ss58 account"))?;
+        let (at, best_number) = self.best()?;
+        let api = self.client.runtime_api();
+        let fundings = api
+            .fundings_of_investor(&at, account.clone())
+            .map_err(client_error)?;
+
+        let mut total_invested: Balance = 0;
+        let mut investments = Vec::with_capacity(fundings.len());
+        for details in fundings {
+            let invested = api
+                .invest_amount(&at, details.funding_id, account.clone())
+                .map_err(client_error)?;
+            total_invested = total_invested.saturating_add(invested);
+            investments.push(Investment {
+                funding: self.funding(details, best_number),
+                invested: invested.to_string(),
+            });
+        }
+        Ok(Portfolio {
+            investor,
+            total_invested: total_invested.to_string(),
+            investments,
+        })
+    }
+
+    fn get_request_tally(&self, request_id: H256) -> Result<Option<RequestTally>> {
+        let (at, best_number) = self.best()?;
+        let api = self.client.runtime_api();
+        let details = match api.request_details(&at, request_id).map_err(client_error)? {
+            Some(details) => details,
+            None => return Ok(None),
+        };
+        let investor_count = api
+            .funding_details(&at, details.funding_id)
+            .map_err(client_error)?
+            .map_or(0, |funding| funding.investor_count);
+        Ok(Some(self.tally(details, investor_count, best_number)))
+    }
+
+    fn get_extrinsic_error(&self, block_hash: H256, index: u32) -> Result<Option<ExtrinsicError>> {
+        let events = module_error::events_at(&self.client, &BlockId::hash(block_hash))
+            .map_err(client_error)?;
+        Ok(module_error::decode_failed_extrinsic(&events, index).map(Into::into))
+    }
+}
+
+/// Start the crowdfunding JSON-RPC HTTP server, it stops when the returned server is dropped.
+pub fn start_http<B, E, RA>(
+    addr: &SocketAddr,
+    client: Arc<Client<B, E, Block, RA>>,
+    slot_duration: u64,
+) -> std::io::Result<jsonrpc_http_server::Server>
+where
+    B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+    E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+    RA: Send + Sync + 'static,
+    Client<B, E, Block, RA>: ProvideRuntimeApi,
+    <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: CrowdfundingApi<Block>,
+{
+    let mut io = IoHandler::new();
+    io.extend_with(Crowdfunding::new(client, slot_duration).to_delegate());
+    jsonrpc_http_server::ServerBuilder::new(io)
+        .threads(1)
+        .start_http(addr)
+}
+
+/// The name of the funding status code.
+pub fn funding_status(status: u64) -> &'static str {
+    match status {
+        0 => "raising",
+        1 => "success",
+        2 => "failure",
+        3 => "dissolved",
+        _ => "unknown",
+    }
+}
+
+/// The name of the request status code.
+pub fn request_status(status: u64) -> &'static str {
+    match status {
+        0 => "pending",
+        1 => "success",
+        2 => "failure",
+        3 => "cancelled",
+        4 => "approved",
+        5 => "disputed",
+        _ => "unknown",
+    }
+}
+
+fn client_error<T: std::fmt::Debug>(e: T) -> Error {
+    Error {
+        code: ErrorCode::ServerError(1),
+        message: "Client error".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}